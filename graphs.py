@@ -0,0 +1,43 @@
+#%%
+# Graphs the files written to results/ by `mc_analysis aggregate`, averaging the
+# result files themselves used to be done here by results.py.
+import os
+from pandas.core.frame import DataFrame
+import pandas
+import seaborn as sns
+import matplotlib.pyplot as plt
+
+#%%
+outDir = "results"
+graphDir = "graphical_results"
+
+
+def graph(filename: str, mean: str, xlim):
+    plt.figure(figsize=(35, 10))
+    dataset: DataFrame = pandas.read_csv(outDir + '/' + filename)
+    figure = sns.lineplot(data=dataset, x="y", y=mean)
+    # Files written before the confidence interval was added only have the mean
+    if "ci_low" in dataset.columns:
+        figure.fill_between(dataset["y"], dataset["ci_low"], dataset["ci_high"], alpha=0.3)
+    figure.set_xlim(xlim)
+    figure.set_title(filename[:-len(".csv")])
+    plt.savefig(graphDir + '/' + filename[:-len(".csv")] + ".png")
+    plt.close()
+
+
+def graphs():
+    sns.set_theme()
+    os.makedirs(graphDir, exist_ok=True)
+    for filename in os.listdir(outDir):
+        if not filename.endswith(".csv") or filename == "comparison.csv":
+            continue
+        columns = pandas.read_csv(outDir + '/' + filename, nrows=0).columns
+        if "avgBlocksPerChunk" in columns:
+            if filename.count("full") > 0:
+                graph(filename, "avgBlocksPerChunk", [-64, 320])
+            else:
+                graph(filename, "avgBlocksPerChunk", [-64, 65])
+        elif "blocksPerSimulation" in columns:
+            graph(filename, "blocksPerSimulation", [-64, 65])
+
+graphs()
@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use crate::error::SimulationError;
+
+/// Where the aggregated results are written, these are read by the website.
+pub const RESULTS_DIRECTORY: &str = "results";
+
+/// Highest y level written to the per block chunk results, nothing of interest generates above it. Air is also written for every y level.
+const CHUNK_Y_LIMIT: i32 = 65;
+
+/// Columns of the chunk result files that identify a row rather than count blocks.
+const CHUNK_KEYS: [&str; 3] = ["chunk_x", "chunk_z", "y"];
+
+/// Two tailed 95% critical values of the t distribution for 1 to 30 degrees of freedom, above that the normal distribution is close enough.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Critical value for a 95% confidence interval with the given degrees of freedom.
+pub fn t_critical(degrees_of_freedom: u64) -> f64 {
+    match degrees_of_freedom {
+        0 => return f64::NAN,
+        1..=30 => return T_95[degrees_of_freedom as usize - 1],
+        _ => return 1.96,
+    }
+}
+
+/// Running summary of every value of a single column at a single y level. Rather than keeping every value the number of times each distinct value was seen is kept, results are block counts so there are few distinct values even across every chunk of many regions.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    /// Keyed by the bits of the value.
+    counts: HashMap<u64, u64>,
+    count: u64,
+    mean: f64,
+    /// Sum of squared differences from the mean, see Welford's algorithm.
+    squares: f64,
+}
+
+impl Summary {
+    pub fn add(&mut self, value: f64) {
+        *self.counts.entry(value.to_bits()).or_insert(0) += 1;
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.squares += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u64 {
+        return self.count;
+    }
+
+    pub fn mean(&self) -> f64 {
+        return self.mean;
+    }
+
+    pub fn median(&self) -> f64 {
+        let mut values: Vec<(f64, u64)> = self
+            .counts
+            .iter()
+            .map(|(bits, n)| (f64::from_bits(*bits), *n))
+            .collect();
+        values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        // Values at the two middle positions, these are the same when the count is odd
+        let lower = self.count.div_ceil(2);
+        let upper = self.count / 2 + 1;
+        let mut seen = 0;
+        let mut low = f64::NAN;
+        for (value, n) in values {
+            if seen < lower && lower <= seen + n {
+                low = value;
+            }
+            if seen < upper && upper <= seen + n {
+                return (low + value) / 2.0;
+            }
+            seen += n;
+        }
+        return f64::NAN;
+    }
+
+    /// Sample standard deviation.
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        return (self.squares / (self.count - 1) as f64).sqrt();
+    }
+
+    /// 95% confidence interval of the mean.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        if self.count < 2 {
+            return (self.mean, self.mean);
+        }
+        let margin = t_critical(self.count - 1) * self.std_dev() / (self.count as f64).sqrt();
+        return (self.mean - margin, self.mean + margin);
+    }
+}
+
+/// The y level of a row of a result file along with every value in it, in the order of the header.
+pub(crate) type Row = (i32, Vec<f64>);
+
+/// Summaries of each column keyed by column and then y level.
+type Columns = BTreeMap<String, BTreeMap<i32, Summary>>;
+
+/// Averages the per region result files in `mining_data/` and `chunk_data/` and writes a file to `results/` for each column, returning the paths written.
+///
+/// Mining results are grouped by technique into `<column>_<technique>.csv` and chunk results into `<column>_chunks.csv` along with `chunks_air_full_range.csv`. Each has a row for every y level with the mean followed by the median, sample standard deviation, 95% confidence interval of the mean, and the number of values.
+pub fn aggregate() -> Result<Vec<String>, SimulationError> {
+    let mut techniques: BTreeMap<String, Columns> = BTreeMap::new();
+    for (path, name) in result_files("mining_data")? {
+        if let Some((_, technique)) = mining_file(&name) {
+            let columns = techniques.entry(technique).or_default();
+            read_columns(&path, &["y"], columns)?;
+        }
+    }
+    let mut chunks = Columns::new();
+    for (path, name) in result_files("chunk_data")? {
+        if name.ends_with("_chunks.csv") {
+            read_columns(&path, &CHUNK_KEYS, &mut chunks)?;
+        }
+    }
+
+    fs::create_dir_all(RESULTS_DIRECTORY).map_err(SimulationError::io(RESULTS_DIRECTORY))?;
+    let mut written = Vec::new();
+    for (technique, columns) in &techniques {
+        for (column, levels) in columns {
+            let path = format!("{}/{}_{}.csv", RESULTS_DIRECTORY, file_safe(column), technique);
+            write_summaries(&path, "blocksPerSimulation", levels, i32::MAX)?;
+            written.push(path);
+        }
+    }
+    for (column, levels) in &chunks {
+        let path = format!("{}/{}_chunks.csv", RESULTS_DIRECTORY, file_safe(column));
+        write_summaries(&path, "avgBlocksPerChunk", levels, CHUNK_Y_LIMIT)?;
+        written.push(path);
+        if column == "air" {
+            let path = format!("{}/chunks_air_full_range.csv", RESULTS_DIRECTORY);
+            write_summaries(&path, "avgBlocksPerChunk", levels, i32::MAX)?;
+            written.push(path);
+        }
+    }
+    return Ok(written);
+}
+
+/// Path and name of every file in the directory, an empty list if the directory does not exist.
+pub(crate) fn result_files(directory: &str) -> Result<Vec<(String, String)>, SimulationError> {
+    if !Path::new(directory).exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory).map_err(SimulationError::io(directory))? {
+        let entry = entry.map_err(SimulationError::io(directory))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        files.push((format!("{}/{}", directory, name), name));
+    }
+    files.sort();
+    return Ok(files);
+}
+
+/// The region and technique of a file named `result-<region>-<technique>.csv`, the region name can contain dashes so it is found by its extension.
+pub(crate) fn mining_file(name: &str) -> Option<(String, String)> {
+    let rest = name.strip_prefix("result-")?.strip_suffix(".csv")?;
+    let (region, technique) = rest.split_once(".mca-")?;
+    return Some((format!("{}.mca", region), technique.to_string()));
+}
+
+/// The header and the values of every row of a result file along with the y level of each row, every value should be a number.
+pub(crate) fn read_rows(path: &str) -> Result<(Vec<String>, Vec<Row>), SimulationError> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.to_string()).collect();
+    let y_index = headers
+        .iter()
+        .position(|h| h == "y")
+        .ok_or_else(|| SimulationError::Malformed(path.to_string(), String::from("there is no y column")))?;
+    let mut rows = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        let mut values = Vec::with_capacity(record.len());
+        for text in record.iter() {
+            values.push(text.trim().parse::<f64>().map_err(|_| {
+                SimulationError::Malformed(
+                    path.to_string(),
+                    format!("'{}' on line {} is not a number", text, line + 2),
+                )
+            })?);
+        }
+        rows.push((values[y_index] as i32, values));
+    }
+    return Ok((headers, rows));
+}
+
+/// Adds every value of a result file to the summaries of its column at the row's y level. Columns listed in `keys` are not summarised.
+fn read_columns(path: &str, keys: &[&str], columns: &mut Columns) -> Result<(), SimulationError> {
+    let (headers, rows) = read_rows(path)?;
+    for (y, values) in rows {
+        for (header, value) in headers.iter().zip(values) {
+            if keys.contains(&header.as_str()) {
+                continue;
+            }
+            columns
+                .entry(header.clone())
+                .or_default()
+                .entry(y)
+                .or_default()
+                .add(value);
+        }
+    }
+    return Ok(());
+}
+
+fn write_summaries(
+    path: &str,
+    mean_name: &str,
+    levels: &BTreeMap<i32, Summary>,
+    y_limit: i32,
+) -> Result<(), SimulationError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    // The mean comes straight after y so that files written by the old results.py can still be read the same way, such as by graphs.py
+    writer.write_record([
+        "y", mean_name, "median", "std_dev", "ci_low", "ci_high", "samples",
+    ])?;
+    for (y, summary) in levels.range(..=y_limit) {
+        let (low, high) = summary.confidence_interval();
+        writer.write_record(&[
+            y.to_string(),
+            summary.mean().to_string(),
+            summary.median().to_string(),
+            summary.std_dev().to_string(),
+            low.to_string(),
+            high.to_string(),
+            summary.count().to_string(),
+        ])?;
+    }
+    writer.flush().map_err(SimulationError::io(path))?;
+    return Ok(());
+}
+
+/// Column names such as `blocks mined` with the spaces replaced so they can be used in a file name.
+fn file_safe(column: &str) -> String {
+    return column.replace(' ', "_");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(values: &[f64]) -> Summary {
+        let mut summary = Summary::default();
+        for value in values {
+            summary.add(*value);
+        }
+        return summary;
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        return (a - b).abs() < 1e-3;
+    }
+
+    #[test]
+    fn median_of_an_odd_count_is_the_middle_value() {
+        assert_eq!(summary(&[5.0, 1.0, 3.0]).median(), 3.0);
+        assert_eq!(summary(&[7.0]).median(), 7.0);
+    }
+
+    #[test]
+    fn median_of_an_even_count_averages_the_middle_values() {
+        assert_eq!(summary(&[4.0, 1.0, 3.0, 2.0]).median(), 2.5);
+        assert_eq!(summary(&[2.0, 2.0, 2.0, 9.0]).median(), 2.0);
+        assert_eq!(summary(&[0.0, 0.0, 1.0, 1.0]).median(), 0.5);
+    }
+
+    #[test]
+    fn median_of_nothing_is_nan() {
+        assert!(Summary::default().median().is_nan());
+    }
+
+    #[test]
+    fn confidence_interval_uses_the_t_distribution() {
+        let summary = summary(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(summary.mean(), 2.5);
+        assert!(close(summary.std_dev(), 1.29099));
+        // 3 degrees of freedom
+        let (low, high) = summary.confidence_interval();
+        assert!(close(high - 2.5, 3.182 * 1.29099 / 2.0));
+        assert!(close(2.5 - low, high - 2.5));
+    }
+
+    #[test]
+    fn confidence_interval_of_a_single_value_is_the_value() {
+        assert_eq!(summary(&[6.0]).confidence_interval(), (6.0, 6.0));
+        assert_eq!(summary(&[6.0]).std_dev(), 0.0);
+    }
+
+    #[test]
+    fn large_samples_use_the_normal_distribution() {
+        assert_eq!(t_critical(30), 2.042);
+        assert_eq!(t_critical(31), 1.96);
+        assert!(t_critical(0).is_nan());
+    }
+}
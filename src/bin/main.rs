@@ -1,144 +1,452 @@
-use std::{fs, io::Error, sync::{Arc, Mutex, mpsc}, thread, time::Instant};
+use std::{env, fs, io::{Error, ErrorKind}, process, sync::{Arc, Mutex, mpsc}, thread, time::Instant};
 
+use mcsim::{CancelToken, ProgramStatus, aggregate::RESULTS_DIRECTORY, checkpoint::{Checkpoint, CHECKPOINT_FILE}, cli::Command, error::SimulationError, mining::Direction, results::{new_run_id, RunMetadata, RunResults, TechniqueMetadata}, cache::ChunkCache, classification::Classification, simulations::{checkpoint_settings, mining_columns, mining_metrics, report, Reach, SimulationContext, SimulationOptions}, techniques::{Parameters, Technique}, ui::{RunSummary, Simulations}};
 use threadpool::ThreadPool;
 
 fn main() -> Result<(), Error> {
-    match mcsim::ui::simulation_target::determine_simulation() {
-        Ok(cont) => {
-            match cont.0 {
-                true => {
-                    // Create mpsc channels
-                    let (transmitter, receiver) = mpsc::channel();
-                    let end = Arc::new(Mutex::new(false));
-                    let sim_end = end.clone();
-                    let title;
-                    let allocated_threads;
-                    let files;
-                    let techniques;
-                    let y_range;
-                    let start = Instant::now();
-                    // Spawn threads for sims
-                    let mut pool = ThreadPool::new(1);
-                    let mut id = 0;
-                    match cont.1.unwrap() {
-                        mcsim::ui::Simulations::Single(tech, file_name, y) => {
-                            pool.execute(move || {
-                                mcsim::simulations::simulate(file_name, tech, y, id, transmitter);
-                            });
-                            title = String::from("Single Simulation");
-                            allocated_threads = 1;
-                            files = 1;
-                            techniques = 1;
-                            y_range = (y, y);
-                        }
-                        mcsim::ui::Simulations::Range(tech, file_name, min, max) => {
-                            pool.execute(move || {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        match mcsim::ui::simulation_target::determine_simulation() {
+            Ok(cont) => {
+                match cont.0 {
+                    true => {
+                        let (simulation, options) = cont.1.unwrap();
+                        run(simulation, options, false)?
+                    }
+                    false => {}
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        match mcsim::cli::parse_args(&args) {
+            Ok(Some(Command::Simulate(simulation, options))) => run(simulation, options, true)?,
+            Ok(Some(Command::Aggregate)) => match mcsim::aggregate::aggregate() {
+                Ok(written) => println!("Wrote {} files to {}/", written.len(), RESULTS_DIRECTORY),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            },
+            Ok(Some(Command::Compare(options))) => {
+                let path = format!("{}/comparison.csv", RESULTS_DIRECTORY);
+                let report = fs::create_dir_all(RESULTS_DIRECTORY)
+                    .map_err(SimulationError::io(RESULTS_DIRECTORY))
+                    .and_then(|_| mcsim::compare::compare(&options))
+                    .and_then(|rankings| {
+                        mcsim::compare::write_report(&path, &rankings)?;
+                        Ok(rankings)
+                    });
+                match report {
+                    Ok(rankings) => {
+                        print!("{}", mcsim::compare::format_report(&rankings));
+                        println!("\nWrote {}", path);
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            Ok(None) => println!("{}", mcsim::cli::USAGE),
+            Err(message) => {
+                eprintln!("{}", message);
+                process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Launches the simulations on a thread pool and monitors them until they finish, either with the tui or by printing status messages to stderr when `headless` is set.
+fn run(simulation: Simulations, mut options: SimulationOptions, headless: bool) -> Result<(), Error> {
+    // Create mpsc channels
+    let (transmitter, receiver) = mpsc::channel();
+    let end = Arc::new(Mutex::new(false));
+    let sim_end = end.clone();
+    let title;
+    let allocated_threads;
+    let files;
+    let techniques;
+    let y_range;
+    // Number of FinishSim messages that will be sent, used for the estimated time left
+    let mut planned_jobs = 0;
+    let start = Instant::now();
+    let cancel = CancelToken::new();
+    // Every run writes long format results and a metadata sidecar beside its other result files
+    let directory = match simulation {
+        Simulations::Chunks(_, _, _) => "chunk_data",
+        _ => "mining_data",
+    };
+    let classification = Arc::new(Classification::load().map_err(io_error)?);
+    // Shared by every worker so that each chunk is only parsed once while it stays in memory
+    let cache = Arc::new(ChunkCache::new(&classification, options.cache_budget));
+    options.reach = simulation_reach(&simulation, &options.direction)?;
+    // Only simulations that write result files are checkpointed
+    let checkpoint = match simulation {
+        Simulations::Single(_, _, _) | Simulations::Chunks(_, _, _) => Checkpoint::disabled(),
+        _ => Checkpoint::open(
+            CHECKPOINT_FILE,
+            options.resume,
+            &checkpoint_settings(&options, &classification),
+        )?,
+    };
+    let run_id = new_run_id();
+    let context = SimulationContext {
+        cache,
+        run: Arc::new(RunResults::create(directory, &run_id, &options.sink).map_err(io_error)?),
+        options,
+        classification,
+        checkpoint: Arc::new(checkpoint),
+        cancel: cancel.clone(),
+        sender: transmitter,
+    };
+    let mode;
+    let mut regions = Vec::new();
+    let mut technique_sets = Vec::new();
+    // Spawn threads for sims
+    let mut pool = ThreadPool::new(1);
+    let mut id = 0;
+    match simulation {
+        Simulations::Single(tech, file_name, y) => {
+            regions.push(file_name.clone());
+            technique_sets.push(TechniqueMetadata {
+                name: tech.name(),
+                parameters: vec![tech.default_parameters()],
+            });
+            let context = context.clone();
+            pool.execute(move || {
+                report(id, context, |context| {
+                    let parameters = tech.default_parameters();
+                    let results = mcsim::simulations::simulate(
+                        context,
+                        file_name.clone(),
+                        &tech,
+                        &parameters,
+                        y,
+                        id,
+                    )?;
+                    let columns = mining_columns(&context.classification);
+                    context.run.write(&file_name, &tech.name(), &parameters, y, &mining_metrics(&results, &columns))?;
+                    context.run.flush()?;
+                    context.sender.send(ProgramStatus::FinishSim(id))?;
+                    Ok(())
+                });
+            });
+            planned_jobs = 1;
+            mode = "single";
+            title = String::from("Single Simulation");
+            allocated_threads = 1;
+            files = 1;
+            techniques = 1;
+            y_range = (y, y);
+        }
+        Simulations::Range(tech, file_name, min, max) => {
+            planned_jobs = remaining_jobs(&context.checkpoint, &file_name, &tech, &[tech.default_parameters()], min, max);
+            regions.push(file_name.clone());
+            technique_sets.push(TechniqueMetadata {
+                name: tech.name(),
+                parameters: vec![tech.default_parameters()],
+            });
+            let context = context.clone();
+            pool.execute(move || {
+                report(id, context, |context| {
+                    if context.options.single_pass {
+                        return mcsim::simulations::simulate_region(
+                            context,
+                            file_name,
+                            std::slice::from_ref(&tech),
+                            None,
+                            max,
+                            min,
+                            id,
+                        );
+                    }
+                    mcsim::simulations::simulate_range(context, file_name, &tech, max, min, id)
+                });
+            });
+            mode = "range";
+            title = String::from("Range Simulation");
+            allocated_threads = 1;
+            files = 1;
+            techniques = 1;
+            y_range = (min, max);
+        }
+        Simulations::Techniques(techs, min, max, threads) => {
+            let mut file_count = 0;
+            pool = ThreadPool::new(threads as usize);
+            for tech in &techs {
+                technique_sets.push(TechniqueMetadata {
+                    name: tech.name(),
+                    parameters: vec![tech.default_parameters()],
+                });
+            }
+            for file in fs::read_dir("regions")? {
+                let file = file?;
+                if mcsim::region_coords(&file.file_name().to_string_lossy()).is_some() {
+                    file_count += 1;
+                    regions.push(file.file_name().to_string_lossy().to_string());
+                    for tech in &techs {
+                        planned_jobs += remaining_jobs(
+                            &context.checkpoint,
+                            &file.file_name().to_string_lossy(),
+                            tech,
+                            &[tech.default_parameters()],
+                            min,
+                            max,
+                        );
+                    }
+                    let techs = techs.clone();
+                    let context = context.clone();
+                    pool.execute(move || {
+                        report(id, context, |context| {
+                            // Every technique shares one pass over the region
+                            if context.options.single_pass {
+                                return mcsim::simulations::simulate_region(
+                                    context,
+                                    file.file_name().to_string_lossy().to_string(),
+                                    &techs,
+                                    None,
+                                    max,
+                                    min,
+                                    id,
+                                );
+                            }
+                            for tech in techs {
                                 mcsim::simulations::simulate_range(
-                                    file_name,
+                                    context,
+                                    file.file_name().to_string_lossy().to_string(),
                                     &tech,
                                     max,
                                     min,
                                     id,
-                                    transmitter,
+                                )?;
+                            }
+                            Ok(())
+                        });
+                    });
+                }
+                id += 1;
+            }
+            mode = "techniques";
+            title = String::from("Technique Comparison Simulation");
+            allocated_threads = threads;
+            files = file_count;
+            techniques = techs.len();
+            y_range = (min, max);
+        }
+        Simulations::TechniqueParameters(techs, sweep, min, max, threads) => {
+            let mut file_count = 0;
+            pool = ThreadPool::new(threads as usize);
+            for tech in &techs {
+                technique_sets.push(TechniqueMetadata {
+                    name: tech.name(),
+                    parameters: sweep
+                        .combinations(tech.as_ref())
+                        .map_err(|e| io_error(SimulationError::Parameters(e)))?,
+                });
+            }
+            for file in fs::read_dir("regions")? {
+                let file = file?;
+                if mcsim::region_coords(&file.file_name().to_string_lossy()).is_some() {
+                    file_count += 1;
+                    regions.push(file.file_name().to_string_lossy().to_string());
+                    for (tech, set) in techs.iter().zip(&technique_sets) {
+                        planned_jobs += remaining_jobs(
+                            &context.checkpoint,
+                            &file.file_name().to_string_lossy(),
+                            tech,
+                            &set.parameters,
+                            min,
+                            max,
+                        );
+                    }
+                    let techs = techs.clone();
+                    let sweep = sweep.clone();
+                    let context = context.clone();
+                    pool.execute(move || {
+                        report(id, context, |context| {
+                            // Every technique and combination shares one pass over the region
+                            if context.options.single_pass {
+                                return mcsim::simulations::simulate_region(
+                                    context,
+                                    file.file_name().to_string_lossy().to_string(),
+                                    &techs,
+                                    Some(&sweep),
+                                    max,
+                                    min,
+                                    id,
                                 );
-                            });
-                            title = String::from("Range Simulation");
-                            allocated_threads = 1;
-                            files = 1;
-                            techniques = 1;
-                            y_range = (min, max);
-                        }
-                        mcsim::ui::Simulations::Techniques(techs, min, max, threads) => {
-                            let mut file_count = 0;
-                            pool = ThreadPool::new(threads as usize);
-                            for file in fs::read_dir("regions").unwrap() {
-                                let file = file.unwrap();
-                                if file.file_name().to_str().unwrap().contains(".mca") {
-                                    file_count += 1;
-                                    let transmitter = transmitter.clone();
-                                    let techs = techs.clone();
-                                    pool.execute(move || {
-                                        for tech in techs {
-                                            mcsim::simulations::simulate_range(
-                                                file.file_name().to_str().unwrap().to_string(),
-                                                &tech,
-                                                max,
-                                                min,
-                                                id,
-                                                transmitter.clone(),
-                                            );
-                                        }
-                                    });
-                                }
-                                id += 1;
                             }
-                            title = String::from("Technique Comparison Simulation");
-                            allocated_threads = threads;
-                            files = file_count;
-                            techniques = techs.len();
-                            y_range = (min, max);
-                        }
-                        mcsim::ui::Simulations::TechniqueParameters(techs, min, max, threads) => {
-                            todo!("yeah, not yet");
-                            title = String::from("Technique Parameters Simulation");
-                            allocated_threads = threads;
-                            files = 0;
-                            techniques = techs.len();
-                            y_range = (min, max);
-                        }
-                        mcsim::ui::Simulations::Chunks(min, max, threads) => {
-                            let mut file_count = 0;
-                            pool = ThreadPool::new(threads as usize);
-                            for file in fs::read_dir("regions").unwrap() {
-                                let file = file.unwrap();
-                                if file.file_name().to_str().unwrap().contains(".mca") {
-                                    file_count += 1;
-                                    let transmitter = transmitter.clone();
-                                    pool.execute(move || {
-                                        mcsim::simulations::chunk_analysis(
-                                            file.file_name().to_str().unwrap().to_string(),
-                                            max,
-                                            min,
-                                            id,
-                                            transmitter,
-                                        );
-                                    });
-                                }
-                                id += 1;
+                            for tech in techs {
+                                mcsim::simulations::simulate_parameters(
+                                    context,
+                                    file.file_name().to_string_lossy().to_string(),
+                                    &tech,
+                                    &sweep,
+                                    max,
+                                    min,
+                                    id,
+                                )?;
                             }
-                            title = String::from("Technique Comparison Simulation");
-                            allocated_threads = threads;
-                            files = file_count;
-                            techniques = 1;
-                            y_range = (min, max);
-                        }
-                    }
-                    // Create thread with sim ui
-                    let handle = thread::spawn(move || {
-                        mcsim::ui::simulation::simulation_ui(
-                            receiver,
-                            sim_end,
-                            title,
-                            files,
-                            allocated_threads,
-                            techniques as u32,
-                            y_range,
-                            start,
-                        )
+                            Ok(())
+                        });
+                    });
+                }
+                id += 1;
+            }
+            mode = "parameters";
+            title = String::from("Technique Parameters Simulation");
+            allocated_threads = threads;
+            files = file_count;
+            techniques = techs.len();
+            y_range = (min, max);
+        }
+        Simulations::Chunks(min, max, threads) => {
+            let mut file_count = 0;
+            pool = ThreadPool::new(threads as usize);
+            for file in fs::read_dir("regions")? {
+                let file = file?;
+                if file.file_name().to_string_lossy().contains(".mca") {
+                    file_count += 1;
+                    planned_jobs += 1;
+                    regions.push(file.file_name().to_string_lossy().to_string());
+                    let context = context.clone();
+                    pool.execute(move || {
+                        report(id, context, |context| {
+                            mcsim::simulations::chunk_analysis(
+                                context,
+                                file.file_name().to_string_lossy().to_string(),
+                                max,
+                                min,
+                                id,
+                            )
+                        });
                     });
-                    pool.join();
-                    {
-                        let mut ending = end.lock().unwrap();
-                        *ending = true;
-                    }
-                    handle.join().unwrap();
-                    println!("Took {} seconds", start.elapsed().as_secs());
                 }
-                false => {}
+                id += 1;
             }
+            mode = "chunks";
+            title = String::from("Technique Comparison Simulation");
+            allocated_threads = threads;
+            files = file_count;
+            techniques = 1;
+            y_range = (min, max);
         }
-        Err(_) => {}
     }
+    let summary = RunSummary {
+        title,
+        files,
+        threads: allocated_threads,
+        techniques: techniques as u32,
+        y_range,
+        planned_jobs,
+        start,
+    };
+    let metadata = RunMetadata::new(
+        &run_id,
+        mode,
+        directory,
+        &context,
+        &summary,
+        regions,
+        technique_sets,
+    );
+    let written = metadata.and_then(|m| {
+        m.write(directory)?;
+        context.run.write_metadata(&m)?;
+        Ok(m)
+    });
+    let mut metadata = match written {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            // Results without their metadata can not be compared, so stop before any more are written
+            cancel.cancel();
+            pool.join();
+            return Err(io_error(e));
+        }
+    };
+    // Create thread with sim ui
+    let monitor_cancel = cancel.clone();
+    let handle = thread::spawn(move || {
+        let monitor = if headless {
+            mcsim::ui::headless::headless_ui
+        } else {
+            mcsim::ui::simulation::simulation_ui
+        };
+        monitor(receiver, sim_end, summary, monitor_cancel)
+    });
+    pool.join();
+    {
+        let mut ending = end.lock().unwrap();
+        *ending = true;
+    }
+    handle.join().unwrap();
+    metadata.finish(cancel.is_cancelled());
+    metadata.write(directory).map_err(io_error)?;
+    context.run.write_metadata(&metadata).map_err(io_error)?;
+    println!("Results of run {} written to {}", run_id, metadata.results);
+    println!("Took {} seconds", start.elapsed().as_secs());
     Ok(())
 }
 
+/// How far every technique of a simulation reaches with each of its sets of parameters, so that sampled starts keep all of them inside the region.
+fn simulation_reach(
+    simulation: &Simulations,
+    direction: &Direction,
+) -> Result<Reach, Error> {
+    let sets: Vec<(&Technique, Vec<Parameters>)> = match simulation {
+        Simulations::Single(tech, _, _) | Simulations::Range(tech, _, _, _) => {
+            vec![(tech, vec![tech.default_parameters()])]
+        }
+        Simulations::Techniques(techs, _, _, _) => techs
+            .iter()
+            .map(|t| (t, vec![t.default_parameters()]))
+            .collect(),
+        Simulations::TechniqueParameters(techs, sweep, _, _, _) => {
+            let mut sets = Vec::new();
+            for tech in techs {
+                let combinations = sweep
+                    .combinations(tech.as_ref())
+                    .map_err(|e| io_error(SimulationError::Parameters(e)))?;
+                sets.push((tech, combinations));
+            }
+            sets
+        }
+        Simulations::Chunks(_, _, _) => Vec::new(),
+    };
+    let mut reach = Reach::default();
+    for (tech, parameters) in sets {
+        for parameters in &parameters {
+            reach = reach.union(Reach::of(tech, parameters, direction).map_err(io_error)?);
+        }
+    }
+    return Ok(reach);
+}
+
+fn io_error(e: SimulationError) -> Error {
+    return Error::new(ErrorKind::Other, e.to_string());
+}
+
+/// Number of y levels that will be simulated for a region file and technique across each set of parameters, leaving out any that the checkpoint already has.
+fn remaining_jobs(
+    checkpoint: &Checkpoint,
+    file: &str,
+    technique: &Technique,
+    parameters: &[Parameters],
+    min: i32,
+    max: i32,
+) -> u64 {
+    let mut jobs = 0;
+    for parameters in parameters {
+        for y in min..max {
+            if !checkpoint.is_done(file, &technique.name(), parameters, y) {
+                jobs += 1;
+            }
+        }
+    }
+    return jobs;
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::{classification::Classification, mining::BlockError};
+
+/// Namespace of the blocks of the base game, ids without a namespace are in it.
+pub const DEFAULT_NAMESPACE: &str = "minecraft";
+
+/// The identity of a block along with its state, such as `minecraft:lava[level=0]` or `minecraft:oak_log[axis=y]`. Blocks read from region files only ever have a name, see [from_anvil](`BlockId::from_anvil`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId {
+    pub namespace: String,
+    pub name: String,
+    /// Block state properties such as `level`, `waterlogged`, or `axis`. Blocks without any, or whose state was not read, have none.
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockId {
+    /// A block in the [DEFAULT_NAMESPACE] without any state.
+    pub fn new(name: &str) -> BlockId {
+        return BlockId {
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            name: name.to_string(),
+            properties: BTreeMap::new(),
+        };
+    }
+
+    /// Parses `[namespace:]name[[key=value,...]]`, the namespace is [DEFAULT_NAMESPACE] when it is left out.
+    pub fn parse(text: &str) -> Result<BlockId, String> {
+        let text = text.trim();
+        let (id, state) = match text.split_once('[') {
+            Some((id, rest)) => {
+                let state = rest
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("The state of '{}' should end with ]", text))?;
+                (id, Some(state))
+            }
+            None => (text, None),
+        };
+        let (namespace, name) = match id.split_once(':') {
+            Some((namespace, name)) => (namespace.trim(), name.trim()),
+            None => (DEFAULT_NAMESPACE, id.trim()),
+        };
+        if namespace.is_empty() || name.is_empty() || name.contains(':') {
+            return Err(format!("'{}' should be in the form namespace:name", text));
+        }
+        let mut properties = BTreeMap::new();
+        for property in state.unwrap_or("").split(',').filter(|p| !p.trim().is_empty()) {
+            let (key, value) = property
+                .split_once('=')
+                .ok_or_else(|| format!("Property '{}' of '{}' should be in the form key=value", property, text))?;
+            properties.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        return Ok(BlockId {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            properties,
+        });
+    }
+
+    /// The block read from a region file. mvp_anvil gives a block as the bare name from its `Block.id`, without a namespace or the state from the section palette, so the block is in the [DEFAULT_NAMESPACE] without any properties. An id that can not be parsed is kept whole as the name.
+    pub fn from_anvil(block: &mvp_anvil::block::Block) -> BlockId {
+        return BlockId::from_id(&block.id);
+    }
+
+    /// Like [from_anvil](`BlockId::from_anvil`) for the id string of the block.
+    pub fn from_id(id: &str) -> BlockId {
+        return BlockId::parse(id).unwrap_or_else(|_| BlockId::new(id));
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        return self.properties.get(key).map(|v| v.as_str());
+    }
+
+    /// Whether this is the block `name` of the [DEFAULT_NAMESPACE], in any state.
+    pub fn is(&self, name: &str) -> bool {
+        return self.namespace == DEFAULT_NAMESPACE && self.name == name;
+    }
+
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.name)?;
+        if !self.properties.is_empty() {
+            let state: Vec<String> = self
+                .properties
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            write!(f, "[{}]", state.join(","))?;
+        }
+        return Ok(());
+    }
+}
+
+/// Index of a block in a [Palette].
+pub type PaletteId = u16;
+
+/// Every distinct block that has been read from the world, each given a small id the first time it is seen so that blocks are stored and compared as numbers rather than as strings. The category of each block is found once when it is added, classifying a block afterwards is a table lookup.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    classification: Classification,
+    blocks: Vec<BlockId>,
+    /// The id string given by mvp_anvil to the id in the palette, so that a block that has been seen is never parsed again.
+    ids: HashMap<String, PaletteId>,
+    /// Index of each block's category in the classification.
+    categories: Vec<Option<usize>>,
+}
+
+impl Palette {
+    pub fn new(classification: &Classification) -> Palette {
+        return Palette {
+            classification: classification.clone(),
+            blocks: Vec::new(),
+            ids: HashMap::new(),
+            categories: Vec::new(),
+        };
+    }
+
+    /// The id of a block read from a region file, adding it to the palette if it has not been seen yet.
+    pub fn intern(&mut self, block: &mvp_anvil::block::Block) -> Result<PaletteId, BlockError> {
+        return self.intern_id(&block.id);
+    }
+
+    /// Like [intern](`Palette::intern`) for the id string of a block as mvp_anvil gives it.
+    pub fn intern_id(&mut self, block: &str) -> Result<PaletteId, BlockError> {
+        if let Some(id) = self.ids.get(block) {
+            return Ok(*id);
+        }
+        if self.blocks.len() > PaletteId::MAX as usize {
+            return Err(BlockError::PaletteFull);
+        }
+        let id = self.blocks.len() as PaletteId;
+        let parsed = BlockId::from_id(block);
+        self.categories.push(self.classification.category(&parsed));
+        self.blocks.push(parsed);
+        self.ids.insert(block.to_string(), id);
+        return Ok(id);
+    }
+
+    pub fn block(&self, id: PaletteId) -> &BlockId {
+        return &self.blocks[id as usize];
+    }
+
+    pub fn classification(&self) -> &Classification {
+        return &self.classification;
+    }
+
+    /// Index in [categories](`Classification::categories`) of the block's category.
+    pub fn category(&self, id: PaletteId) -> Option<usize> {
+        return self.categories[id as usize];
+    }
+
+    pub fn is_ore(&self, id: PaletteId) -> bool {
+        return self
+            .category(id)
+            .is_some_and(|i| self.classification.categories()[i].ore);
+    }
+
+    /// Number of blocks in each category, in the same order as [categories](`Classification::categories`).
+    pub fn count<I: IntoIterator<Item = PaletteId>>(&self, blocks: I) -> Vec<u32> {
+        let mut counts = vec![0; self.classification.categories().len()];
+        for block in blocks {
+            if let Some(index) = self.category(block) {
+                counts[index] += 1;
+            }
+        }
+        return counts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_in_the_default_namespace() {
+        let block = BlockId::parse(" iron_ore ").unwrap();
+        assert_eq!(block, BlockId::new("iron_ore"));
+        assert!(block.is("iron_ore"));
+        assert_eq!(block.to_string(), "minecraft:iron_ore");
+    }
+
+    #[test]
+    fn namespaces_and_states_are_parsed() {
+        let block = BlockId::parse("create:zinc_ore").unwrap();
+        assert_eq!((block.namespace.as_str(), block.name.as_str()), ("create", "zinc_ore"));
+        assert!(!block.is("zinc_ore"));
+        let block = BlockId::parse("minecraft:oak_log[axis=y, waterlogged=false]").unwrap();
+        assert_eq!(block.property("axis"), Some("y"));
+        assert_eq!(block.property("waterlogged"), Some("false"));
+        assert_eq!(block.to_string(), "minecraft:oak_log[axis=y,waterlogged=false]");
+        assert_eq!(BlockId::parse(&block.to_string()), Ok(block));
+    }
+
+    #[test]
+    fn malformed_ids_are_not_parsed() {
+        for text in ["", ":stone", "minecraft:", "a:b:c", "lava[level=0", "lava[level]"].iter() {
+            assert!(BlockId::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn unparsable_ids_are_kept_whole() {
+        assert_eq!(BlockId::from_id("a:b:c").name, "a:b:c");
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard,
+    },
+};
+
+use mvp_anvil::chunk::Chunk;
+
+use crate::{
+    block::{Palette, PaletteId},
+    classification::Classification,
+    mining::{BlockError, WORLD_BOTTOM, WORLD_TOP},
+};
+
+/// Memory budgets that can be chosen in the ui, in MiB.
+pub const CACHE_BUDGETS: [usize; 5] = [256, 512, 1024, 2048, 4096];
+
+/// Number of 16 block tall sections between the bottom and top of the world.
+pub const CHUNK_SECTIONS: usize = ((WORLD_TOP - WORLD_BOTTOM) / 16) as usize;
+
+const SECTION_BLOCKS: usize = 16 * 16 * 16;
+
+/// Memory that a chunk parsed by mvp_anvil is assumed to take, mvp_anvil does not report the size of its chunks.
+const PARSED_CHUNK_BYTES: usize = 64 * 1024;
+
+/// The most memory that a cached chunk can take, once every section has been interned. Every chunk is counted at this size so that the budget is not exceeded as more of its sections are read.
+const CHUNK_BYTES: usize = PARSED_CHUNK_BYTES + CHUNK_SECTIONS * SECTION_BLOCKS * mem::size_of::<PaletteId>();
+
+/// The region coordinates of the region file along with the x and z of the chunk within it.
+pub type ChunkKey = ((i32, i32), usize, usize);
+
+/// A chunk along with the palette ids of its blocks. Each 16x16x16 section is interned the first time a block inside of it is read, most techniques only reach into a few sections of a chunk.
+pub struct CachedChunk {
+    chunk: Chunk,
+    sections: Vec<Mutex<Option<Arc<Vec<PaletteId>>>>>,
+}
+
+impl CachedChunk {
+    fn new(chunk: Chunk) -> CachedChunk {
+        return CachedChunk {
+            chunk,
+            sections: (0..CHUNK_SECTIONS).map(|_| Mutex::new(None)).collect(),
+        };
+    }
+
+    /// Palette ids of every block of a section ordered by y, then z, then x. Sections are numbered from the bottom of the world.
+    pub fn section(&self, section: usize, palette: &RwLock<Palette>) -> Result<Arc<Vec<PaletteId>>, BlockError> {
+        let mut interned = self.sections[section].lock().unwrap();
+        if let Some(blocks) = interned.as_ref() {
+            return Ok(blocks.clone());
+        }
+        let bottom = WORLD_BOTTOM + section as i32 * 16;
+        let mut read = Vec::with_capacity(SECTION_BLOCKS);
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    read.push(self.chunk.get_block(x, bottom + y, z));
+                }
+            }
+        }
+        let mut palette = palette.write().unwrap();
+        let mut blocks = Vec::with_capacity(SECTION_BLOCKS);
+        for block in &read {
+            blocks.push(palette.intern(block)?);
+        }
+        let blocks = Arc::new(blocks);
+        *interned = Some(blocks.clone());
+        return Ok(blocks);
+    }
+}
+
+/// How well the [ChunkCache] is doing since it was created.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Number of chunks currently held.
+    pub chunks: usize,
+    /// Memory counted against the budget for the chunks currently held, in bytes.
+    pub bytes: usize,
+    /// In bytes.
+    pub budget: usize,
+}
+
+impl CacheStats {
+    /// Fraction of chunk requests that did not need the chunk to be parsed.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        return self.hits as f64 / total as f64;
+    }
+}
+
+/// Least recently used chunks in the order they were used.
+#[derive(Default)]
+struct CacheState {
+    chunks: HashMap<ChunkKey, (Arc<CachedChunk>, u64)>,
+    /// Keyed by the tick that each chunk was last used at.
+    order: BTreeMap<u64, ChunkKey>,
+    clock: u64,
+}
+
+/// Chunks that have been parsed from region files, shared by every worker thread so that a chunk is parsed once rather than once for every y level and technique that reaches into it. When the chunks held would take more than the memory budget the least recently used are dropped.
+///
+/// The budget is not a hard bound on memory. The size of a chunk is estimated, see [PARSED_CHUNK_BYTES], and each [World](`crate::World`) keeps the chunk that it is reading until it moves on to the next one even if the cache has dropped it.
+///
+/// Palette ids are only meaningful to the palette that gave them, so the cache holds the palette that every cached chunk is interned into.
+pub struct ChunkCache {
+    budget: usize,
+    palette: RwLock<Palette>,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl ChunkCache {
+    /// A cache that holds at most `budget` MiB of chunks, blocks are classified with `classification`.
+    pub fn new(classification: &Classification, budget: usize) -> ChunkCache {
+        return ChunkCache {
+            budget: budget * 1024 * 1024,
+            palette: RwLock::new(Palette::new(classification)),
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        };
+    }
+
+    /// Every block that has been read through the cache so far.
+    pub fn palette(&self) -> RwLockReadGuard<'_, Palette> {
+        return self.palette.read().unwrap();
+    }
+
+    /// The palette itself, for [section](`CachedChunk::section`).
+    pub fn shared_palette(&self) -> &RwLock<Palette> {
+        return &self.palette;
+    }
+
+    /// Gets a chunk, calling `load` to parse it when it is not cached. `load` is called without holding the cache so other threads are not held up while a chunk is parsed, its errors are returned as they are.
+    pub fn get<F: FnOnce() -> Result<Chunk, BlockError>>(&self, key: ChunkKey, load: F) -> Result<Arc<CachedChunk>, BlockError> {
+        {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            let tick = state.clock;
+            state.clock += 1;
+            if let Some((chunk, used)) = state.chunks.get_mut(&key) {
+                let chunk = chunk.clone();
+                let last = mem::replace(used, tick);
+                state.order.remove(&last);
+                state.order.insert(tick, key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(chunk);
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let loaded = Arc::new(CachedChunk::new(load()?));
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let tick = state.clock;
+        state.clock += 1;
+        // Another thread may have loaded the same chunk in the meantime, its copy is kept so that sections are only interned once
+        let chunk = match state.chunks.get(&key) {
+            Some((chunk, _)) => chunk.clone(),
+            None => {
+                state.chunks.insert(key, (loaded.clone(), tick));
+                state.order.insert(tick, key);
+                loaded
+            }
+        };
+        while state.chunks.len() * CHUNK_BYTES > self.budget {
+            let oldest = match state.order.keys().next() {
+                Some(oldest) => *oldest,
+                None => break,
+            };
+            let evicted = state.order.remove(&oldest).unwrap();
+            state.chunks.remove(&evicted);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        return Ok(chunk);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let chunks = self.state.lock().unwrap().chunks.len();
+        return CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            chunks,
+            bytes: chunks * CHUNK_BYTES,
+            budget: self.budget,
+        };
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{error::SimulationError, techniques::{parameters_text, Parameters}};
+
+/// Location of the manifest that records which simulations have finished.
+pub const CHECKPOINT_FILE: &str = "mining_data/checkpoint.txt";
+
+/// Record of every (region, technique, parameters, y) that has been simulated and written to a result file. A line is appended and flushed as each y level finishes so that a run which crashes or is stopped can be resumed without redoing finished work.
+pub struct Checkpoint {
+    path: PathBuf,
+    resuming: bool,
+    done: HashSet<String>,
+    // None when nothing is recorded
+    file: Option<Mutex<File>>,
+}
+
+impl Checkpoint {
+    /// Opens the manifest at `path`. When resuming the finished work that it lists is loaded, otherwise it is cleared since the result files will be recreated.
+    ///
+    /// The first line of the manifest holds the `settings` of the run that wrote it, see [checkpoint_settings](`crate::simulations::checkpoint_settings`). Resuming with other settings is refused since the rows already written would not be comparable with the new ones.
+    pub fn open<P: AsRef<Path>>(path: P, resume: bool, settings: &str) -> io::Result<Checkpoint> {
+        let path = path.as_ref().to_path_buf();
+        let settings = format!("settings,{}", settings);
+        let mut done = HashSet::new();
+        if resume && path.exists() {
+            let mut lines = BufReader::new(File::open(&path)?).lines();
+            if let Some(line) = lines.next() {
+                let line = line?;
+                if line != settings {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "{} was written by a run with other settings ({}), start a new run instead of resuming",
+                            path.display(),
+                            line.trim_start_matches("settings,")
+                        ),
+                    ));
+                }
+            }
+            for line in lines {
+                done.insert(line?);
+            }
+        } else {
+            fs::remove_file(&path).ok();
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", settings)?;
+            file.flush()?;
+        }
+        return Ok(Checkpoint {
+            path,
+            resuming: resume,
+            done,
+            file: Some(Mutex::new(file)),
+        });
+    }
+
+    /// A checkpoint that nothing has been recorded in and that does not record anything, for simulations that do not write result files.
+    pub fn disabled() -> Checkpoint {
+        return Checkpoint {
+            path: PathBuf::new(),
+            resuming: false,
+            done: HashSet::new(),
+            file: None,
+        };
+    }
+
+    /// Whether finished work is being skipped, result files are appended to rather than recreated when this is set.
+    pub fn resuming(&self) -> bool {
+        return self.resuming;
+    }
+
+    pub fn is_done(&self, region: &str, technique: &str, parameters: &Parameters, y: i32) -> bool {
+        return self.done.contains(&key(region, technique, parameters, y));
+    }
+
+    /// Marks a simulation as finished, this should only be called once its results have been flushed.
+    pub fn record(
+        &self,
+        region: &str,
+        technique: &str,
+        parameters: &Parameters,
+        y: i32,
+    ) -> Result<(), SimulationError> {
+        let mut file = match &self.file {
+            Some(file) => file.lock().unwrap(),
+            None => return Ok(()),
+        };
+        writeln!(file, "{}", key(region, technique, parameters, y))
+            .and_then(|_| file.flush())
+            .map_err(SimulationError::io(&self.path))?;
+        return Ok(());
+    }
+}
+
+fn key(region: &str, technique: &str, parameters: &Parameters, y: i32) -> String {
+    return format!("{},{},{},{}", region, technique, parameters_text(parameters), y);
+}
+
+/// Opens a csv result file. When resuming an existing file is appended to, otherwise the file is recreated and `header` is written first.
+///
+/// Rows are flushed before they are recorded in the checkpoint, so a run that stopped in between leaves rows that will be simulated again. When resuming, the rows that `finished` does not accept are removed before appending so that they are not written twice.
+pub fn result_writer<S, F>(
+    path: &str,
+    header: &[S],
+    resume: bool,
+    finished: F,
+) -> Result<csv::Writer<File>, SimulationError>
+where
+    S: AsRef<[u8]>,
+    F: Fn(&csv::StringRecord) -> bool,
+{
+    let existing = resume && fs::metadata(path).is_ok_and(|m| m.len() > 0);
+    if existing {
+        let mut reader = csv::Reader::from_path(path)?;
+        let mut rows = Vec::new();
+        let mut unfinished = false;
+        for row in reader.records() {
+            let row = row?;
+            if finished(&row) {
+                rows.push(row);
+            } else {
+                unfinished = true;
+            }
+        }
+        if unfinished {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(reader.headers()?)?;
+            for row in &rows {
+                writer.write_record(row)?;
+            }
+            writer.flush().map_err(SimulationError::io(path))?;
+        }
+    } else {
+        fs::remove_file(path).ok();
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(SimulationError::io(path))?;
+    let mut writer = csv::Writer::from_writer(file);
+    if !existing {
+        writer.write_record(header)?;
+        writer.flush().map_err(SimulationError::io(path))?;
+    }
+    return Ok(writer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("mcsim-checkpoint-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        return directory.join(name);
+    }
+
+    fn parameters(spacing: i32) -> Parameters {
+        let mut parameters = Parameters::new();
+        parameters.insert(String::from("spacing"), spacing);
+        return parameters;
+    }
+
+    #[test]
+    fn resumes_with_the_same_settings() {
+        let path = scratch("same.txt");
+        let checkpoint = Checkpoint::open(&path, false, "sampling=fixed").unwrap();
+        checkpoint.record("r.0.0.mca", "tunnel", &parameters(3), 11).unwrap();
+        let resumed = Checkpoint::open(&path, true, "sampling=fixed").unwrap();
+        assert!(resumed.is_done("r.0.0.mca", "tunnel", &parameters(3), 11));
+        assert!(!resumed.is_done("r.0.0.mca", "tunnel", &parameters(4), 11));
+        assert!(!resumed.is_done("r.0.0.mca", "tunnel", &parameters(3), 12));
+    }
+
+    #[test]
+    fn refuses_to_resume_with_other_settings() {
+        let path = scratch("other.txt");
+        let checkpoint = Checkpoint::open(&path, false, "sampling=fixed").unwrap();
+        checkpoint.record("r.0.0.mca", "tunnel", &parameters(3), 11).unwrap();
+        let error = Checkpoint::open(&path, true, "sampling=grid:4").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        // Starting over does not need the settings to match
+        let restarted = Checkpoint::open(&path, false, "sampling=grid:4").unwrap();
+        assert!(!restarted.is_done("r.0.0.mca", "tunnel", &parameters(3), 11));
+    }
+
+    #[test]
+    fn removes_rows_that_were_not_recorded() {
+        let path = scratch("rows.csv");
+        let path = path.to_str().unwrap();
+        let mut writer = result_writer(path, &["y", "value"], false, |_| true).unwrap();
+        writer.write_record(["11", "1.5"]).unwrap();
+        writer.write_record(["12", "2.5"]).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        let mut writer = result_writer(path, &["y", "value"], true, |row| &row[0] == "11").unwrap();
+        writer.write_record(["12", "3.5"]).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "y,value\n11,1.5\n12,3.5\n");
+    }
+}
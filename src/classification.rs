@@ -0,0 +1,309 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+};
+
+use serde::Serialize;
+
+use crate::{
+    block::{BlockId, DEFAULT_NAMESPACE},
+    error::SimulationError,
+};
+
+/// Location of the classification, it keeps the name of the older file that only mapped blocks to ores.
+pub const CLASSIFICATION_FILE: &str = "ValidBlocks.txt";
+
+/// Categories that the older format of [CLASSIFICATION_FILE], which only mapped blocks to categories, can use without them being ore.
+const LEGACY_COUNTED: [&str; 3] = ["air", "lava", "water"];
+
+/// Blocks that were always counted before the classification could declare categories, they are added to files in the older format unless a rule already covers them.
+const LEGACY_BLOCKS: [(&str, &str); 4] = [
+    ("air", "air"),
+    ("cave_air", "air"),
+    ("lava", "lava"),
+    ("flowing_lava", "lava"),
+];
+
+/// A group of blocks that is counted together and written as a single result column.
+#[derive(Clone, Debug, Serialize)]
+pub struct Category {
+    pub name: String,
+    /// What a single block of the category is worth, the value column of a simulation is the total of every block counted times its weight.
+    pub weight: f64,
+    /// Ores are mined out when following veins, shown in the monitor, and ranked by compare. Other categories such as lava or air are only counted.
+    pub ore: bool,
+}
+
+/// Which category, if any, each block belongs to. This is read once from [CLASSIFICATION_FILE] and shared by every simulation.
+///
+/// The file is made of lines in these forms, blank lines and lines starting with `#` are ignored.
+/// - `<block>:<category>` puts a block in a category, such as `iron_ore:iron` or `minecraft:iron_ore:iron`. Blocks are read from region files by name alone, see [BlockId::from_anvil], so a rule matches the block in every state and rules with another namespace or with state properties are refused. When there is more than one rule for a block the first one is used.
+/// - `ore <category> [weight <value>]` declares an ore category, categories that are only used by blocks are ores with a weight of 1.
+/// - `count <category> [weight <value>]` declares a category that is counted but is not ore, these have a weight of 0 unless one is given.
+///
+/// Categories become result columns in the order that they first appear.
+///
+/// A file without any `ore` or `count` lines is in the older format that only mapped blocks to ores. Its `air`, `lava`, and `water` categories are counted rather than ore, and `air` and `lava` come first with the blocks that were always counted in them, `air`, `cave_air`, `lava`, and `flowing_lava`, unless a rule already covers those blocks.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Classification {
+    categories: Vec<Category>,
+    /// Each rule's block, with its namespace, to category name, kept for the run metadata.
+    blocks: BTreeMap<String, String>,
+    /// Index of the category of each block, keyed by the name of the block.
+    #[serde(skip)]
+    rules: HashMap<String, usize>,
+}
+
+impl Classification {
+    /// Reads [CLASSIFICATION_FILE].
+    pub fn load() -> Result<Classification, SimulationError> {
+        let text = fs::read_to_string(CLASSIFICATION_FILE)
+            .map_err(SimulationError::io(CLASSIFICATION_FILE))?;
+        return Classification::parse(&text);
+    }
+
+    pub fn parse(text: &str) -> Result<Classification, SimulationError> {
+        let mut classification = Classification::default();
+        let legacy = !text
+            .lines()
+            .any(|line| matches!(line.split_whitespace().next(), Some("ore" | "count")));
+        if legacy {
+            for name in ["air", "lava"] {
+                classification.category_index(name);
+            }
+        }
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || SimulationError::ValidBlocks(number + 1, line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [kind @ ("ore" | "count"), name, rest @ ..] => {
+                    let ore = *kind == "ore";
+                    let weight = match rest {
+                        [] => {
+                            if ore {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        }
+                        ["weight", value] => value.parse::<f64>().map_err(|_| invalid())?,
+                        _ => return Err(invalid()),
+                    };
+                    let index = classification.category_index(name);
+                    classification.categories[index].weight = weight;
+                    classification.categories[index].ore = ore;
+                }
+                _ => {
+                    // The block can have a namespace so the category is after the last colon
+                    let (block, category) = line.rsplit_once(':').ok_or_else(invalid)?;
+                    let category = category.trim();
+                    if category.is_empty() || category.contains(']') {
+                        return Err(invalid());
+                    }
+                    let block = BlockId::parse(block).map_err(|_| invalid())?;
+                    if block.namespace != DEFAULT_NAMESPACE || !block.properties.is_empty() {
+                        return Err(SimulationError::UnreadableBlock(
+                            number + 1,
+                            block.to_string(),
+                        ));
+                    }
+                    classification.add_rule(block, category);
+                }
+            }
+        }
+        if legacy {
+            for (block, category) in LEGACY_BLOCKS {
+                let block = BlockId::new(block);
+                if classification.category(&block).is_none() {
+                    classification.add_rule(block, category);
+                }
+            }
+            for category in &mut classification.categories {
+                if LEGACY_COUNTED.contains(&category.name.as_str()) {
+                    category.ore = false;
+                    category.weight = 0.0;
+                }
+            }
+        }
+        return Ok(classification);
+    }
+
+    /// Puts the block in the category unless an earlier rule already has, so that the first rule for a block wins.
+    fn add_rule(&mut self, block: BlockId, category: &str) {
+        let index = self.category_index(category);
+        self.blocks
+            .entry(block.to_string())
+            .or_insert_with(|| category.to_string());
+        self.rules.entry(block.name).or_insert(index);
+    }
+
+    /// Index of the category with the name, it is added as an ore with a weight of 1 if it has not been seen yet.
+    fn category_index(&mut self, name: &str) -> usize {
+        if let Some(index) = self.categories.iter().position(|c| c.name == name) {
+            return index;
+        }
+        self.categories.push(Category {
+            name: name.to_string(),
+            weight: 1.0,
+            ore: true,
+        });
+        return self.categories.len() - 1;
+    }
+
+    pub fn categories(&self) -> &[Category] {
+        return &self.categories;
+    }
+
+    /// Names of every ore category.
+    pub fn ores(&self) -> Vec<&str> {
+        return self
+            .categories
+            .iter()
+            .filter(|c| c.ore)
+            .map(|c| c.name.as_str())
+            .collect();
+    }
+
+    /// Index in [categories](`Classification::categories`) of the block's category.
+    pub fn category(&self, block: &BlockId) -> Option<usize> {
+        if block.namespace != DEFAULT_NAMESPACE {
+            return None;
+        }
+        return self.rules.get(&block.name).copied();
+    }
+
+    /// Total of each count times the weight of its category.
+    pub fn value(&self, counts: &[u32]) -> f64 {
+        return self
+            .categories
+            .iter()
+            .zip(counts)
+            .map(|(category, count)| category.weight * *count as f64)
+            .sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(classification: &Classification) -> Vec<(&str, bool, f64)> {
+        return classification
+            .categories()
+            .iter()
+            .map(|c| (c.name.as_str(), c.ore, c.weight))
+            .collect();
+    }
+
+    fn category<'a>(classification: &'a Classification, block: &str) -> Option<&'a str> {
+        return classification
+            .category(&BlockId::new(block))
+            .map(|index| classification.categories()[index].name.as_str());
+    }
+
+    #[test]
+    fn categories_are_ore_unless_counted() {
+        let classification = Classification::parse(
+            "count lava\nore iron weight 2\nlava:lava\niron_ore:iron\nchest:chests\nwater:water",
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&classification),
+            vec![
+                ("lava", false, 0.0),
+                ("iron", true, 2.0),
+                ("chests", true, 1.0),
+                ("water", true, 1.0),
+            ]
+        );
+        assert_eq!(classification.ores(), vec!["iron", "chests", "water"]);
+        // Files with declarations do not get the blocks of the older format
+        assert_eq!(category(&classification, "air"), None);
+        assert_eq!(category(&classification, "flowing_lava"), None);
+        assert_eq!(classification.value(&[5, 3, 1, 1]), 8.0);
+    }
+
+    #[test]
+    fn older_format_counts_air_and_lava() {
+        let classification = Classification::parse(
+            "iron_ore:iron\ndeepslate_iron_ore:iron\nwater:water\ncave_air:caves\ndiamond_ore:diamonds",
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&classification),
+            vec![
+                ("air", false, 0.0),
+                ("lava", false, 0.0),
+                ("iron", true, 1.0),
+                ("water", false, 0.0),
+                ("caves", true, 1.0),
+                ("diamonds", true, 1.0),
+            ]
+        );
+        assert_eq!(category(&classification, "air"), Some("air"));
+        assert_eq!(category(&classification, "flowing_lava"), Some("lava"));
+        // A rule in the file wins over the blocks that are added
+        assert_eq!(category(&classification, "cave_air"), Some("caves"));
+        assert_eq!(
+            category(&classification, "deepslate_iron_ore"),
+            Some("iron")
+        );
+    }
+
+    #[test]
+    fn first_rule_for_a_block_wins() {
+        let classification =
+            Classification::parse("ore iron\niron_ore:iron\niron_ore:other").unwrap();
+        assert_eq!(category(&classification, "iron_ore"), Some("iron"));
+        assert_eq!(
+            classification
+                .blocks
+                .get("minecraft:iron_ore")
+                .map(|c| c.as_str()),
+            Some("iron")
+        );
+    }
+
+    #[test]
+    fn rejects_blocks_that_are_never_read() {
+        let classification = Classification::parse("minecraft:iron_ore:iron").unwrap();
+        assert_eq!(category(&classification, "iron_ore"), Some("iron"));
+        for text in [
+            "ore lava_source\nlava[level=0]:lava_source",
+            "ore zinc\ncreate:zinc_ore:zinc",
+        ] {
+            assert!(
+                matches!(
+                    Classification::parse(text),
+                    Err(SimulationError::UnreadableBlock(2, _))
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for text in [
+            "iron_ore",
+            "ore",
+            "ore iron weight",
+            "ore iron weight heavy",
+            "iron_ore:",
+        ] {
+            assert!(
+                matches!(
+                    Classification::parse(text),
+                    Err(SimulationError::ValidBlocks(1, _))
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+}
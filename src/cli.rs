@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use crate::{compare::CompareOptions, mining::Direction, simulations::{parse_start, Sampling, SimulationOptions}, sinks::Sink, techniques::{ParameterRange, ParameterSweep, Technique, TechniqueRegistry}, ui::Simulations};
+
+pub const USAGE: &str = "Usage: mc_analysis [SUBCOMMAND] [OPTIONS]
+
+Running without a subcommand launches the interactive ui.
+
+Subcommands:
+    single      --technique <name> --region <file> --y <y>
+    range       --technique <name> --region <file> --min <y> --max <y>
+    techniques  --technique <name>... --min <y> --max <y> [--threads <n>]
+    parameters  --technique <name>... --sweep <name=range>... --min <y> --max <y> [--threads <n>]
+    chunk       --min <y> --max <y> [--threads <n>]
+    aggregate   Summarise mining_data/ and chunk_data/ into results/
+    compare     [--technique <name>...] [--band <n>] [--min <y> --max <y>]
+                Rank techniques by ore per block for each ore and band of y levels
+    help        Print this message
+
+Options:
+    -t, --technique <name>  Technique to simulate (e.g. branch, poke), may be repeated
+    -r, --region <file>     Region file inside of regions/ (e.g. r.0.0.mca)
+    -y, --y <y>             Y level for a single simulation
+        --min <y>           Lowest y level of a range (inclusive)
+        --max <y>           Highest y level of a range (exclusive)
+    -j, --threads <n>       Number of worker threads, defaults to 1
+    -s, --sweep <name=range>
+                            Parameter range for a parameter simulation, may be repeated.
+                            Ranges are 'value', 'start..end', or 'start..end:step' with an inclusive end
+                            (e.g. branch_spacing=3..8 or branch_length=32..160:32)
+        --follow-veins      Mine out ore veins that are exposed by the technique
+        --avoid-hazards     Abandon tunnels that expose lava, water, or caves
+        --start <x,z>       Coordinates within the region to start from, each 0 to 511, defaults to 255,255
+        --direction <dir>   Direction the technique heads in (north, south, east, west), defaults to south
+        --sampling <mode>   Start positions to average over: fixed, random:<count>[:<seed>], or grid:<count>,
+                            defaults to fixed which only uses --start
+        --resume            Skip work that a previous range, techniques, or parameters run finished
+                            and append to its result files
+        --sink <format>     Format of the run's long format results: csv, jsonl, or sqlite, defaults to csv
+        --chunk-cache <MiB> Memory budget of the chunk cache shared by every worker thread, defaults to 1024
+        --single-pass       Simulate every y level and technique of a region in one pass over its chunks,
+                            for range, techniques, and parameters simulations
+        --game-version <version>
+                            Version of Minecraft that generated the region files, recorded in the run's
+                            metadata, defaults to unknown
+        --band <n>          Number of y levels in each band that compare ranks techniques over, defaults to 16";
+
+/// What the program was asked to do from the command line.
+pub enum Command {
+    Simulate(Simulations, SimulationOptions),
+    /// Summarise the result files, see [aggregate](`crate::aggregate::aggregate`).
+    Aggregate,
+    /// Rank techniques against each other, see [compare](`crate::compare::compare`).
+    Compare(CompareOptions),
+}
+
+/// Flags collected from the command line before they are checked against the chosen subcommand.
+#[derive(Default)]
+struct Arguments {
+    techniques: Vec<Technique>,
+    region: Option<String>,
+    y: Option<i32>,
+    min: Option<i32>,
+    max: Option<i32>,
+    threads: Option<u32>,
+    sweep: ParameterSweep,
+    options: SimulationOptions,
+    band: Option<i32>,
+}
+
+/// Builds the same [Simulations](`crate::ui::Simulations`) that the ui would from command line arguments. The first argument is expected to be the subcommand, the program name should already be removed.
+///
+/// Returns `None` when help was requested and the message that should be printed to the user on an error.
+pub fn parse_args(args: &[String]) -> Result<Option<Command>, String> {
+    let (command, rest) = match args.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    if command == "help" || command == "--help" || command == "-h" {
+        return Ok(None);
+    }
+    if command == "aggregate" {
+        if let Some(flag) = rest.first() {
+            return Err(format!("Unknown option '{}' for aggregate\n\n{}", flag, USAGE));
+        }
+        return Ok(Some(Command::Aggregate));
+    }
+
+    let arguments = parse_flags(rest)?;
+    if command == "compare" {
+        let mut options = CompareOptions::default();
+        options.band = arguments.band.unwrap_or(options.band);
+        options.techniques = arguments.techniques.iter().map(|t| t.name()).collect();
+        if arguments.min.is_some() || arguments.max.is_some() {
+            options.y_range = Some(y_range(&arguments)?);
+        }
+        return Ok(Some(Command::Compare(options)));
+    }
+    if arguments.options.single_pass && (command == "single" || command == "chunk") {
+        return Err(format!(
+            "'--single-pass' can only be used with range, techniques, and parameters simulations, not {}",
+            command
+        ));
+    }
+    let sim = match command.as_str() {
+        "single" => Simulations::Single(
+            single_technique(&arguments)?,
+            region(&arguments)?,
+            required(arguments.y, "--y")?,
+        ),
+        "range" => {
+            let (min, max) = y_range(&arguments)?;
+            Simulations::Range(single_technique(&arguments)?, region(&arguments)?, min, max)
+        }
+        "techniques" => {
+            let (min, max) = y_range(&arguments)?;
+            Simulations::Techniques(
+                techniques(&arguments)?,
+                min,
+                max,
+                arguments.threads.unwrap_or(1),
+            )
+        }
+        "parameters" => {
+            let (min, max) = y_range(&arguments)?;
+            let techniques = techniques(&arguments)?;
+            for technique in &techniques {
+                arguments.sweep.combinations(technique.as_ref())?;
+            }
+            for range in &arguments.sweep.ranges {
+                if !techniques
+                    .iter()
+                    .any(|t| t.parameters().iter().any(|p| p.name == range.name))
+                {
+                    return Err(format!("No selected technique has a parameter named '{}'", range.name));
+                }
+            }
+            Simulations::TechniqueParameters(
+                techniques,
+                arguments.sweep.clone(),
+                min,
+                max,
+                arguments.threads.unwrap_or(1),
+            )
+        }
+        "chunk" => {
+            let (min, max) = y_range(&arguments)?;
+            Simulations::Chunks(min, max, arguments.threads.unwrap_or(1))
+        }
+        _ => return Err(format!("Unknown subcommand '{}'\n\n{}", command, USAGE)),
+    };
+
+    let regions = crate::ui::verify_directory_structure()
+        .map_err(|e| format!("Failed to set up the working directory: {}", e))?;
+    if !regions {
+        return Err(String::from(
+            "The regions directory is currently empty, please place .mca files in the directory before continuing.",
+        ));
+    }
+    if let Some(file) = &arguments.region {
+        if !Path::new("regions").join(file).exists() {
+            return Err(format!("Region file 'regions/{}' does not exist", file));
+        }
+    }
+    return Ok(Some(Command::Simulate(sim, arguments.options)));
+}
+
+fn parse_flags(args: &[String]) -> Result<Arguments, String> {
+    let registry = TechniqueRegistry::load();
+    for error in registry.errors() {
+        eprintln!("Failed to load technique definition {}", error);
+    }
+    let mut arguments = Arguments::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("Missing value for '{}'", flag))
+        };
+        match flag.as_str() {
+            "-t" | "--technique" => {
+                let name = value()?;
+                match registry.get(name) {
+                    Some(technique) => arguments.techniques.push(technique),
+                    None => return Err(format!("Unknown technique '{}'", name)),
+                }
+            }
+            "-r" | "--region" => arguments.region = Some(value()?.clone()),
+            "-y" | "--y" => arguments.y = Some(number(flag, value()?)?),
+            "--min" => arguments.min = Some(number(flag, value()?)?),
+            "--max" => arguments.max = Some(number(flag, value()?)?),
+            "-j" | "--threads" => {
+                let threads = number(flag, value()?)?;
+                if threads < 1 {
+                    return Err(String::from("Number of threads should be greater than 0"));
+                }
+                arguments.threads = Some(threads);
+            }
+            "--follow-veins" => arguments.options.follow_veins = true,
+            "--avoid-hazards" => arguments.options.avoid_hazards = true,
+            "--resume" => arguments.options.resume = true,
+            "--single-pass" => arguments.options.single_pass = true,
+            "--start" => arguments.options.start = parse_start(value()?)?,
+            "--direction" => arguments.options.direction = Direction::parse(value()?)?,
+            "--sampling" => arguments.options.sampling = Sampling::parse(value()?)?,
+            "--sink" => arguments.options.sink = Sink::parse(value()?)?,
+            "--game-version" => arguments.options.game_version = Some(value()?.trim().to_string()),
+            "--chunk-cache" => {
+                let budget = number(flag, value()?)?;
+                if budget < 1 {
+                    return Err(String::from("Chunk cache should be at least 1 MiB"));
+                }
+                arguments.options.cache_budget = budget;
+            }
+            "--band" => {
+                let band = number(flag, value()?)?;
+                if band < 1 {
+                    return Err(String::from("Band should be at least 1 y level"));
+                }
+                arguments.band = Some(band);
+            }
+            "-s" | "--sweep" => {
+                let text = value()?;
+                match text.split_once('=') {
+                    Some((name, range)) => arguments
+                        .sweep
+                        .ranges
+                        .push(ParameterRange::parse(name.trim(), range)?),
+                    None => return Err(format!("Sweep '{}' should be in the form name=range", text)),
+                }
+            }
+            _ => return Err(format!("Unknown option '{}'\n\n{}", flag, USAGE)),
+        }
+    }
+    return Ok(arguments);
+}
+
+fn number<T: std::str::FromStr>(flag: &str, text: &str) -> Result<T, String> {
+    text.parse::<T>()
+        .map_err(|_| format!("Failed to parse '{}' for '{}'", text, flag))
+}
+
+fn required<T>(value: Option<T>, flag: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("Missing required option '{}'", flag))
+}
+
+fn single_technique(arguments: &Arguments) -> Result<Technique, String> {
+    match arguments.techniques.as_slice() {
+        [technique] => Ok(technique.clone()),
+        [] => Err(String::from("Missing required option '--technique'")),
+        _ => Err(String::from("Only one technique can be used for this simulation")),
+    }
+}
+
+fn techniques(arguments: &Arguments) -> Result<Vec<Technique>, String> {
+    if arguments.techniques.is_empty() {
+        return Err(String::from("Missing required option '--technique'"));
+    }
+    return Ok(arguments.techniques.clone());
+}
+
+fn region(arguments: &Arguments) -> Result<String, String> {
+    let file = required(arguments.region.clone(), "--region")?;
+    if crate::region_coords(&file).is_none() {
+        return Err(format!("'{}' is not a region file named r.<x>.<z>.mca", file));
+    }
+    return Ok(file);
+}
+
+fn y_range(arguments: &Arguments) -> Result<(i32, i32), String> {
+    let min = required(arguments.min, "--min")?;
+    let max = required(arguments.max, "--max")?;
+    if min >= max {
+        return Err(String::from("--min should be less than --max"));
+    }
+    return Ok((min, max));
+}
+
@@ -0,0 +1,457 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::{
+    aggregate::{mining_file, read_rows, result_files, t_critical},
+    classification::Classification,
+    error::SimulationError,
+};
+
+/// Number of resamples used for each bootstrap confidence interval.
+const BOOTSTRAP_SAMPLES: usize = 2000;
+
+/// Seed of the bootstrap resampling, fixed so that the same results always give the same report.
+const BOOTSTRAP_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Choices for the comparison report.
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    /// Number of y levels in each band, the results of every y level in a band are added together before techniques are compared.
+    pub band: i32,
+    /// Names of the techniques to compare, every technique with results when empty.
+    pub techniques: Vec<String>,
+    /// Lowest (inclusive) and highest (exclusive) y levels to include, every y level when not set.
+    pub y_range: Option<(i32, i32)>,
+}
+
+impl Default for CompareOptions {
+    fn default() -> CompareOptions {
+        return CompareOptions {
+            band: 16,
+            techniques: Vec::new(),
+            y_range: None,
+        };
+    }
+}
+
+/// Where a technique places for a single ore in a single band of y levels.
+#[derive(Clone, Debug)]
+pub struct Ranking {
+    pub ore: String,
+    /// Lowest and highest y level of the band, both inclusive.
+    pub band: (i32, i32),
+    /// Starts at 1 for the technique that finds the most ore per block mined.
+    pub rank: usize,
+    /// Starts at 1 for the technique that finds the most ore per block exposed.
+    pub exposed_rank: usize,
+    pub technique: String,
+    /// Number of regions that the technique was simulated in for this band.
+    pub regions: usize,
+    /// Mean across regions of ore found per block mined.
+    pub per_mined: f64,
+    /// 95% bootstrap confidence interval of `per_mined`.
+    pub per_mined_interval: (f64, f64),
+    /// Mean across regions of ore found per block exposed.
+    pub per_exposed: f64,
+    pub per_exposed_interval: (f64, f64),
+    /// Test against the technique ranked directly above, not set for the first technique.
+    pub test: Option<PairedTest>,
+}
+
+/// Paired t-test of ore per block mined between two techniques over the regions that both were simulated in.
+#[derive(Clone, Debug)]
+pub struct PairedTest {
+    /// The technique ranked directly above.
+    pub against: String,
+    pub regions: usize,
+    /// Mean of the differences, this technique minus the one above.
+    pub mean_difference: f64,
+    pub t: f64,
+    /// Whether the difference is significant at the 5% level, when it is not the two techniques can not be told apart.
+    pub significant: bool,
+}
+
+/// Ore found, blocks mined, and blocks exposed in a band of a single region.
+type Totals = (f64, f64, f64);
+
+/// Ranks techniques for each ore and band of y levels using the per region result files in `mining_data/`, by ore per block mined and separately by ore per block exposed. Each technique is tested against the one ranked directly above it by ore per block mined rather than against the best technique. The ores are the ore categories of the classification, files written before an ore was added are left out of its rankings. Results are ordered by ore, then band, then rank.
+pub fn compare(options: &CompareOptions) -> Result<Vec<Ranking>, SimulationError> {
+    if options.band < 1 {
+        return Err(SimulationError::Parameters(String::from(
+            "Band should be at least 1 y level",
+        )));
+    }
+    let classification = Classification::load()?;
+    let ores = classification.ores();
+    // Keyed by ore, band start, technique, and region
+    let mut totals: BTreeMap<(usize, i32), BTreeMap<String, BTreeMap<String, Totals>>> =
+        BTreeMap::new();
+    for (path, name) in result_files("mining_data")? {
+        let (region, technique) = match mining_file(&name) {
+            Some(file) => file,
+            None => continue,
+        };
+        if !options.techniques.is_empty() && !options.techniques.contains(&technique) {
+            continue;
+        }
+        let (headers, rows) = read_rows(&path)?;
+        let column = |name: &str| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                SimulationError::Malformed(path.clone(), format!("there is no {} column", name))
+            })
+        };
+        let mined = column("blocks mined")?;
+        let exposed = column("blocks exposed")?;
+        let columns: Vec<(usize, usize)> = ores
+            .iter()
+            .enumerate()
+            .filter_map(|(ore, name)| headers.iter().position(|h| h == name).map(|i| (ore, i)))
+            .collect();
+        for (y, values) in rows {
+            if let Some((min, max)) = options.y_range {
+                if y < min || y >= max {
+                    continue;
+                }
+            }
+            let band = y.div_euclid(options.band) * options.band;
+            for (ore, index) in &columns {
+                let region_totals = totals
+                    .entry((*ore, band))
+                    .or_default()
+                    .entry(technique.clone())
+                    .or_default()
+                    .entry(region.clone())
+                    .or_insert((0.0, 0.0, 0.0));
+                region_totals.0 += values[*index];
+                region_totals.1 += values[mined];
+                region_totals.2 += values[exposed];
+            }
+        }
+    }
+
+    let mut state = BOOTSTRAP_SEED;
+    let mut rankings = Vec::new();
+    for ((ore, band), techniques) in totals {
+        let mut band_rankings = Vec::new();
+        for (technique, regions) in techniques {
+            let per_mined = ratios(&regions, |t| t.1);
+            let per_exposed = ratios(&regions, |t| t.2);
+            if per_mined.is_empty() {
+                continue;
+            }
+            band_rankings.push((
+                Ranking {
+                    ore: ores[ore].to_string(),
+                    band: (band, band + options.band - 1),
+                    rank: 0,
+                    exposed_rank: 0,
+                    technique,
+                    regions: per_mined.len(),
+                    per_mined: mean(per_mined.values()),
+                    per_mined_interval: bootstrap(&per_mined, &mut state),
+                    per_exposed: mean(per_exposed.values()),
+                    per_exposed_interval: bootstrap(&per_exposed, &mut state),
+                    test: None,
+                },
+                per_mined,
+            ));
+        }
+        rankings.extend(rank_band(band_rankings));
+    }
+    return Ok(rankings);
+}
+
+/// Sets both ranks of the techniques in a band along with the test of each against the one above, alongside every ranking is its ore per block mined in each region.
+fn rank_band(mut band_rankings: Vec<(Ranking, BTreeMap<String, f64>)>) -> Vec<Ranking> {
+    band_rankings.sort_by(|a, b| highest_first(a.0.per_exposed, b.0.per_exposed));
+    for (i, ranking) in band_rankings.iter_mut().enumerate() {
+        ranking.0.exposed_rank = i + 1;
+    }
+    band_rankings.sort_by(|a, b| highest_first(a.0.per_mined, b.0.per_mined));
+    for i in 0..band_rankings.len() {
+        band_rankings[i].0.rank = i + 1;
+        if i > 0 {
+            let test = paired_test(&band_rankings[i - 1], &band_rankings[i]);
+            band_rankings[i].0.test = Some(test);
+        }
+    }
+    return band_rankings
+        .into_iter()
+        .map(|(ranking, _)| ranking)
+        .collect();
+}
+
+/// Orders from highest to lowest with NaN last, which is the mean of a technique that exposed no blocks in any region.
+fn highest_first(a: f64, b: f64) -> Ordering {
+    return match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.total_cmp(&a),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    };
+}
+
+/// Ore per block for each region, regions where no blocks were counted are left out.
+fn ratios<F: Fn(&Totals) -> f64>(
+    regions: &BTreeMap<String, Totals>,
+    blocks: F,
+) -> BTreeMap<String, f64> {
+    return regions
+        .iter()
+        .filter(|(_, t)| blocks(t) > 0.0)
+        .map(|(region, t)| (region.clone(), t.0 / blocks(t)))
+        .collect();
+}
+
+fn mean<'a, I: Iterator<Item = &'a f64>>(values: I) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        return f64::NAN;
+    }
+    return sum / count as f64;
+}
+
+/// xorshift, the state can never be zero.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    return *state;
+}
+
+/// Percentile bootstrap 95% confidence interval of the mean, regions are resampled with replacement.
+fn bootstrap(values: &BTreeMap<String, f64>, state: &mut u64) -> (f64, f64) {
+    let values: Vec<f64> = values.values().cloned().collect();
+    if values.len() < 2 {
+        let value = mean(values.iter());
+        return (value, value);
+    }
+    let mut means: Vec<f64> = (0..BOOTSTRAP_SAMPLES)
+        .map(|_| {
+            let sum: f64 = (0..values.len())
+                .map(|_| values[(next_random(state) % values.len() as u64) as usize])
+                .sum();
+            sum / values.len() as f64
+        })
+        .collect();
+    means.sort_by(|a, b| a.total_cmp(b));
+    let low = (BOOTSTRAP_SAMPLES as f64 * 0.025) as usize;
+    let high = (BOOTSTRAP_SAMPLES as f64 * 0.975) as usize - 1;
+    return (means[low], means[high]);
+}
+
+fn paired_test(
+    above: &(Ranking, BTreeMap<String, f64>),
+    below: &(Ranking, BTreeMap<String, f64>),
+) -> PairedTest {
+    let differences: Vec<f64> = below
+        .1
+        .iter()
+        .filter_map(|(region, value)| above.1.get(region).map(|a| value - a))
+        .collect();
+    let n = differences.len();
+    let mean_difference = mean(differences.iter());
+    let mut t = f64::NAN;
+    let mut significant = false;
+    if n >= 2 {
+        let variance = differences
+            .iter()
+            .map(|d| (d - mean_difference).powi(2))
+            .sum::<f64>()
+            / (n - 1) as f64;
+        let error = (variance / n as f64).sqrt();
+        if error > 0.0 {
+            t = mean_difference / error;
+            significant = t.abs() > t_critical(n as u64 - 1);
+        } else {
+            // Every region differs by the same amount, any difference at all is certain
+            significant = mean_difference != 0.0;
+        }
+    }
+    return PairedTest {
+        against: above.0.technique.clone(),
+        regions: n,
+        mean_difference,
+        t,
+        significant,
+    };
+}
+
+/// Writes a row for each ranking.
+pub fn write_report(path: &str, rankings: &[Ranking]) -> Result<(), SimulationError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record([
+        "ore",
+        "y_min",
+        "y_max",
+        "rank",
+        "exposed_rank",
+        "technique",
+        "regions",
+        "ore_per_mined",
+        "ore_per_mined_ci_low",
+        "ore_per_mined_ci_high",
+        "ore_per_exposed",
+        "ore_per_exposed_ci_low",
+        "ore_per_exposed_ci_high",
+        "compared_to",
+        "paired_regions",
+        "mean_difference",
+        "t",
+        "significant",
+    ])?;
+    for r in rankings {
+        let test = match &r.test {
+            Some(test) => vec![
+                test.against.clone(),
+                test.regions.to_string(),
+                test.mean_difference.to_string(),
+                test.t.to_string(),
+                test.significant.to_string(),
+            ],
+            None => vec![String::new(); 5],
+        };
+        let mut record = vec![
+            r.ore.clone(),
+            r.band.0.to_string(),
+            r.band.1.to_string(),
+            r.rank.to_string(),
+            r.exposed_rank.to_string(),
+            r.technique.clone(),
+            r.regions.to_string(),
+            r.per_mined.to_string(),
+            r.per_mined_interval.0.to_string(),
+            r.per_mined_interval.1.to_string(),
+            r.per_exposed.to_string(),
+            r.per_exposed_interval.0.to_string(),
+            r.per_exposed_interval.1.to_string(),
+        ];
+        record.extend(test);
+        writer.write_record(&record)?;
+    }
+    writer.flush().map_err(SimulationError::io(path))?;
+    return Ok(());
+}
+
+/// A plain text version of the report with one block for each ore and band, techniques that can not be told apart from the one above them are flagged.
+pub fn format_report(rankings: &[Ranking]) -> String {
+    let mut report = String::from(
+        "Ranked by ore per block mined, the rank by ore per block exposed is in brackets.\n\
+         Each technique is compared with the one ranked directly above it, not with the first,\n\
+         by a paired t-test of ore per block mined over the regions that both were simulated in.\n",
+    );
+    let mut current = None;
+    for r in rankings {
+        if current != Some((&r.ore, r.band)) {
+            current = Some((&r.ore, r.band));
+            report.push_str(&format!("\n{} y {} to {}\n", r.ore, r.band.0, r.band.1));
+        }
+        let flag = match &r.test {
+            Some(test) if !test.significant => {
+                format!("  not significantly different from {}", test.against)
+            }
+            _ => String::new(),
+        };
+        report.push_str(&format!(
+            "  {}. ({}) {:<16} {:.5} per mined [{:.5}, {:.5}]  {:.5} per exposed [{:.5}, {:.5}]  {} regions{}\n",
+            r.rank,
+            r.exposed_rank,
+            r.technique,
+            r.per_mined,
+            r.per_mined_interval.0,
+            r.per_mined_interval.1,
+            r.per_exposed,
+            r.per_exposed_interval.0,
+            r.per_exposed_interval.1,
+            r.regions,
+            flag
+        ));
+    }
+    return report;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(
+        technique: &str,
+        per_mined: &[f64],
+        per_exposed: f64,
+    ) -> (Ranking, BTreeMap<String, f64>) {
+        let regions: BTreeMap<String, f64> = per_mined
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("r.{}.0.mca", i), *value))
+            .collect();
+        let ranking = Ranking {
+            ore: String::from("iron"),
+            band: (0, 15),
+            rank: 0,
+            exposed_rank: 0,
+            technique: technique.to_string(),
+            regions: regions.len(),
+            per_mined: mean(regions.values()),
+            per_mined_interval: (0.0, 0.0),
+            per_exposed,
+            per_exposed_interval: (0.0, 0.0),
+            test: None,
+        };
+        return (ranking, regions);
+    }
+
+    #[test]
+    fn ranks_by_mined_and_by_exposed() {
+        let rankings = rank_band(vec![
+            ranking("quarry", &[0.01, 0.02, 0.03], 0.004),
+            ranking("branch", &[0.03, 0.04, 0.05], 0.002),
+            ranking("tunnel", &[0.02, 0.03, 0.04], 0.003),
+        ]);
+        let ranks: Vec<(&str, usize, usize)> = rankings
+            .iter()
+            .map(|r| (r.technique.as_str(), r.rank, r.exposed_rank))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![("branch", 1, 3), ("tunnel", 2, 2), ("quarry", 3, 1)]
+        );
+    }
+
+    #[test]
+    fn techniques_that_expose_nothing_are_ranked_last() {
+        let mut regions = BTreeMap::new();
+        regions.insert(String::from("r.0.0.mca"), (3.0, 100.0, 0.0));
+        regions.insert(String::from("r.1.0.mca"), (2.0, 100.0, 0.0));
+        let per_exposed = ratios(&regions, |t| t.2);
+        assert!(per_exposed.is_empty());
+        let rankings = rank_band(vec![
+            ranking("quarry", &[0.01, 0.02], 0.004),
+            ranking("none", &[0.03, 0.04], mean(per_exposed.values())),
+            ranking("tunnel", &[0.02, 0.03], 0.003),
+        ]);
+        let ranks: Vec<(&str, usize, usize)> = rankings
+            .iter()
+            .map(|r| (r.technique.as_str(), r.rank, r.exposed_rank))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![("none", 1, 3), ("tunnel", 2, 2), ("quarry", 3, 1)]
+        );
+        assert_eq!(highest_first(f64::NAN, f64::NAN), Ordering::Equal);
+    }
+
+    #[test]
+    fn tests_against_the_technique_directly_above() {
+        let rankings = rank_band(vec![
+            ranking("branch", &[0.03, 0.04, 0.05], 0.002),
+            ranking("tunnel", &[0.02, 0.03, 0.04], 0.003),
+            ranking("quarry", &[0.019, 0.021, 0.02], 0.004),
+        ]);
+        assert!(rankings[0].test.is_none());
+        let tunnel = rankings[1].test.as_ref().unwrap();
+        assert_eq!(tunnel.against, "branch");
+        // Every region is exactly 0.01 lower
+        assert!(tunnel.significant);
+        let quarry = rankings[2].test.as_ref().unwrap();
+        assert_eq!(quarry.against, "tunnel");
+        assert_eq!(quarry.regions, 3);
+        assert!(!quarry.significant);
+    }
+}
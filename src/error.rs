@@ -0,0 +1,107 @@
+use std::{error::Error, fmt, io, path::Path, sync::mpsc::SendError};
+
+use crate::{mining::BlockError, ProgramStatus};
+
+/// Everything that can stop a simulation part way through. These are sent to the monitoring ui with [FailedSim](`ProgramStatus::FailedSim`) rather than taking down the worker thread.
+#[derive(Debug)]
+pub enum SimulationError {
+    /// A file or directory could not be read or written, the path is included.
+    Io(String, io::Error),
+    /// A result file could not be written.
+    Csv(csv::Error),
+    /// The results database could not be written.
+    Sqlite(rusqlite::Error),
+    /// A block that the simulation needed could not be read from the world.
+    Block(BlockError),
+    /// A line of ValidBlocks.txt that is not a `block:category` mapping or an `ore`/`count` declaration, along with its line number.
+    ValidBlocks(usize, String),
+    /// A rule of ValidBlocks.txt for a block that is never read from region files, one with a namespace other than `minecraft` or with state properties, along with its line number.
+    UnreadableBlock(usize, String),
+    /// The name of a region file that is not in the form `r.<x>.<z>.mca`.
+    RegionName(String),
+    /// A result file that could not be aggregated, along with its path and what is wrong with it.
+    Malformed(String, String),
+    /// The parameter sweep can not be used with the technique.
+    Parameters(String),
+    /// The monitoring ui stopped receiving status updates.
+    Disconnected,
+    /// The simulation panicked, such as when a region file is corrupt.
+    Panic(String),
+    /// The simulation was stopped early by a [CancelToken](`crate::CancelToken`).
+    Cancelled,
+}
+
+impl SimulationError {
+    /// Wraps an io error with the path of the file that caused it, for use with `map_err`.
+    pub fn io<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> SimulationError {
+        let path = path.as_ref().display().to_string();
+        return move |e| SimulationError::Io(path, e);
+    }
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::Io(path, e) => write!(f, "Failed to access {}: {}", path, e),
+            SimulationError::Csv(e) => write!(f, "Failed to write results: {}", e),
+            SimulationError::Sqlite(e) => write!(f, "Failed to write results to the database: {}", e),
+            SimulationError::Block(e) => write!(f, "{}", e),
+            SimulationError::ValidBlocks(line, text) => write!(
+                f,
+                "Line {} of ValidBlocks.txt should be in the form block:category, ore <category> [weight <value>], or count <category> [weight <value>], found '{}'",
+                line, text
+            ),
+            SimulationError::UnreadableBlock(line, block) => write!(
+                f,
+                "Line {} of ValidBlocks.txt is for '{}', but blocks are only read from region files by name so rules can not give a namespace other than minecraft or state properties",
+                line, block
+            ),
+            SimulationError::RegionName(name) => {
+                write!(f, "'{}' is not a region file named r.<x>.<z>.mca", name)
+            }
+            SimulationError::Malformed(path, reason) => {
+                write!(f, "'{}' is not a valid result file: {}", path, reason)
+            }
+            SimulationError::Parameters(e) => write!(f, "{}", e),
+            SimulationError::Disconnected => write!(f, "The monitor stopped receiving updates"),
+            SimulationError::Panic(message) => write!(f, "Simulation panicked: {}", message),
+            SimulationError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl Error for SimulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SimulationError::Io(_, e) => Some(e),
+            SimulationError::Csv(e) => Some(e),
+            SimulationError::Sqlite(e) => Some(e),
+            SimulationError::Block(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BlockError> for SimulationError {
+    fn from(e: BlockError) -> SimulationError {
+        return SimulationError::Block(e);
+    }
+}
+
+impl From<csv::Error> for SimulationError {
+    fn from(e: csv::Error) -> SimulationError {
+        return SimulationError::Csv(e);
+    }
+}
+
+impl From<rusqlite::Error> for SimulationError {
+    fn from(e: rusqlite::Error) -> SimulationError {
+        return SimulationError::Sqlite(e);
+    }
+}
+
+impl From<SendError<ProgramStatus>> for SimulationError {
+    fn from(_: SendError<ProgramStatus>) -> SimulationError {
+        return SimulationError::Disconnected;
+    }
+}
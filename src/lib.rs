@@ -1,41 +1,354 @@
-use std::{collections::VecDeque, fs, iter::FromIterator, path::Path, process, time::Instant};
+use std::{collections::{hash_map::Entry, HashMap, HashSet}, path::{Path, PathBuf}, sync::{Arc, RwLockReadGuard, atomic::{AtomicBool, AtomicU64, Ordering}}, time::Instant};
 
-use mvp_anvil::{chunk::Chunk, region::Region};
-use techniques::Technique;
-use tui::widgets::ListState;
+use mvp_anvil::region::Region;
 
+use crate::{block::{Palette, PaletteId}, cache::{CacheStats, CachedChunk, ChunkCache}};
+
+pub mod aggregate;
+pub mod block;
+pub mod cache;
+pub mod checkpoint;
+pub mod classification;
+pub mod cli;
+pub mod compare;
+pub mod error;
 pub mod mining;
+pub mod results;
 pub mod simulations;
+pub mod sinks;
 pub mod techniques;
 pub mod ui;
 
-#[derive(Clone)]
 pub enum ProgramStatus {
-    // id, simulation_type, region_file, start_time, y
-    StartingSim(u32, Technique, String, Instant, i32),
-    // id, activity, blocks, exposed, lava, ores
-    UpdateSim(u32, String, u32, u32, u32, u32),
+    // id, technique_name, region_file, start_time, y
+    StartingSim(u32, String, String, Instant, i32),
+    // id, activity, blocks, exposed, lava, count of each ore category in the order of the classification
+    UpdateSim(u32, String, u32, u32, u32, Vec<(String, u32)>),
     // id, end_time
-    FinishSim(u32)
+    FinishSim(u32),
+    // id, the reason that the simulation stopped
+    FailedSim(u32, error::SimulationError),
+    // id, units of work finished, total units of work
+    ProgressSim(u32, u64, u64),
+    // hits, misses, and memory use of the shared chunk cache
+    CacheStats(cache::CacheStats),
+}
+
+/// Number of blocks that have been read from region files, used to report throughput.
+static BLOCKS_READ: AtomicU64 = AtomicU64::new(0);
+
+/// Total number of blocks read by every simulation so far.
+pub fn blocks_read() -> u64 {
+    return BLOCKS_READ.load(Ordering::Relaxed);
 }
-pub struct CachingRegion<'a> {
-    region: &'a Region<'a>,
-    chunks: Vec<Vec<Option<Chunk>>>,
+
+pub(crate) fn count_blocks_read(blocks: u64) {
+    BLOCKS_READ.fetch_add(blocks, Ordering::Relaxed);
 }
+/// Shared flag that asks every running simulation to stop. Simulations check it between y levels and between the segments of an excavation, the results that have already been written are kept.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        return CancelToken::default();
+    }
 
-impl<'a> CachingRegion<'a> {
-    fn new(region: &'a Region<'a>) -> CachingRegion<'a> {
-        return CachingRegion {
-            region,
-            chunks: vec![vec![None; 32]; 32]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.0.load(Ordering::SeqCst);
+    }
+
+    /// Returns [Cancelled](`error::SimulationError::Cancelled`) once the token has been cancelled, for use with `?`.
+    pub fn check(&self) -> Result<(), error::SimulationError> {
+        if self.is_cancelled() {
+            return Err(error::SimulationError::Cancelled);
         }
+        return Ok(());
+    }
+}
+
+/// Width of a region in chunks.
+pub const REGION_CHUNKS: i32 = 32;
+
+/// The chunk that a [World] read last along with the sections of it that have been read, so that the shared [ChunkCache] is only locked when reading moves into another chunk.
+struct WorldChunk {
+    /// Absolute chunk coordinates.
+    coords: (i32, i32),
+    chunk: Arc<CachedChunk>,
+    sections: Vec<Option<Arc<Vec<PaletteId>>>>,
+}
+
+/// Blocks copied out of the world by [preload](`World::preload`), every column holds the blocks from `bottom` up to but not including `top`.
+#[derive(Default)]
+struct Columns {
+    bottom: i32,
+    top: i32,
+    blocks: HashMap<(i32, i32), Vec<PaletteId>>,
+}
+
+/// Every region file in a directory, addressed with absolute world coordinates so that techniques can cross from one region into the next. Region files are named `r.<x>.<z>.mca` after their region coordinates and are only loaded the first time a chunk inside of them is needed that is not already in the [ChunkCache].
+///
+/// Only the chunk that was read last is kept by the world itself, every other chunk is left to the cache so that the chunks it drops are freed.
+pub struct World {
+    directory: PathBuf,
+    cache: Arc<ChunkCache>,
+    // None when there is no file for the region
+    regions: HashMap<(i32, i32), Option<Region<'static>>>,
+    last: Option<WorldChunk>,
+    columns: Columns,
+}
+
+impl World {
+    /// Chunks are shared with every other world that uses the same `cache`, blocks are classified with the cache's palette.
+    pub fn new<P: Into<PathBuf>>(directory: P, cache: &Arc<ChunkCache>) -> World {
+        return World {
+            directory: directory.into(),
+            cache: cache.clone(),
+            regions: HashMap::new(),
+            last: None,
+            columns: Columns::default(),
+        };
+    }
+
+    /// Every block that has been read from the world so far.
+    pub fn palette(&self) -> RwLockReadGuard<'_, Palette> {
+        return self.cache.palette();
     }
 
-    fn get_chunk(&mut self, x: usize, z: usize) -> &Chunk {
-        if self.chunks[x][z].is_none() {
-            self.chunks[x][z] = Some(self.region.get_chunk(x as u32, z as u32));
+    pub fn cache_stats(&self) -> CacheStats {
+        return self.cache.stats();
+    }
+
+    /// The region at the region coordinates, loading it if it has not been used yet. Returns None when the directory has no file for the region.
+    fn region<'r>(
+        regions: &'r mut HashMap<(i32, i32), Option<Region<'static>>>,
+        directory: &Path,
+        x: i32,
+        z: i32,
+    ) -> Result<Option<&'r Region<'static>>, mining::BlockError> {
+        if let Entry::Vacant(entry) = regions.entry((x, z)) {
+            let path = directory.join(region_file_name(x, z));
+            let region = if path.exists() {
+                // mvp_anvil only takes the path as a string
+                let text = path
+                    .to_str()
+                    .ok_or_else(|| mining::BlockError::RegionPath(path.display().to_string()))?;
+                Some(Region::from_file(text.to_string()))
+            } else {
+                None
+            };
+            entry.insert(region);
         }
+        return Ok(regions[&(x, z)].as_ref());
+    }
 
-        return self.chunks[x][z].as_ref().unwrap();
+    /// Gets the block at the absolute coordinates from whichever region contains it, see [get_block](`mining::get_block`). Each call is for a single block so it is counted in [blocks_read].
+    pub fn get_block(&mut self, coords: (i32, i32, i32)) -> Result<PaletteId, mining::BlockError> {
+        let (x, y, z) = coords;
+        if let Some(column) = self.columns.blocks.get(&(x, z)) {
+            if (self.columns.bottom..self.columns.top).contains(&y) {
+                count_blocks_read(1);
+                return Ok(column[(y - self.columns.bottom) as usize]);
+            }
+        }
+        let location = BlockLocation::of(coords)?;
+        count_blocks_read(1);
+        let (region_x, region_z) = location.region;
+        let missing = mining::BlockError::MissingRegion(coords, region_x, region_z);
+        if !matches!(&self.last, Some(c) if c.coords == location.chunk) {
+            if let Some(None) = self.regions.get(&location.region) {
+                return Err(missing);
+            }
+            let key = (location.region, location.local_chunk.0, location.local_chunk.1);
+            let (regions, directory) = (&mut self.regions, &self.directory);
+            let chunk = self.cache.get(key, || {
+                match World::region(regions, directory, region_x, region_z)? {
+                    Some(region) => Ok(region.get_chunk(key.1 as u32, key.2 as u32)),
+                    None => Err(missing),
+                }
+            })?;
+            self.last = Some(WorldChunk {
+                coords: location.chunk,
+                chunk,
+                sections: vec![None; cache::CHUNK_SECTIONS],
+            });
+        }
+        let chunk = self.last.as_mut().unwrap();
+        if chunk.sections[location.section].is_none() {
+            chunk.sections[location.section] =
+                Some(chunk.chunk.section(location.section, self.cache.shared_palette())?);
+        }
+        return Ok(chunk.sections[location.section].as_ref().unwrap()[location.index]);
     }
-}
\ No newline at end of file
+
+    /// A world that is made up rather than read from region files, with the block from `block` at each position in the columns of `x` and `z` over the whole height of the world. Reading outside of those columns gives [MissingRegion](`mining::BlockError::MissingRegion`).
+    #[cfg(test)]
+    pub(crate) fn generated<F: Fn((i32, i32, i32)) -> &'static str>(
+        cache: &Arc<ChunkCache>,
+        x: std::ops::Range<i32>,
+        z: std::ops::Range<i32>,
+        block: F,
+    ) -> World {
+        let mut world = World::new(std::env::temp_dir().join("mcsim-no-regions"), cache);
+        let mut palette = cache.shared_palette().write().unwrap();
+        for x in x {
+            for z in z.clone() {
+                let column = (mining::WORLD_BOTTOM..mining::WORLD_TOP)
+                    .map(|y| palette.intern_id(block((x, y, z))).unwrap())
+                    .collect();
+                world.columns.blocks.insert((x, z), column);
+            }
+        }
+        world.columns.bottom = mining::WORLD_BOTTOM;
+        world.columns.top = mining::WORLD_TOP;
+        return world;
+    }
+
+    /// Copies the blocks from `bottom` up to `top` of every column out of the world, after which reading them no longer goes through the [ChunkCache]. The columns are read a chunk at a time so that each chunk is only fetched once however small the cache is. Columns in regions that have no file are left out, reading them gives the usual error.
+    ///
+    /// The copies take two bytes for each block and are kept until the world is dropped or preloaded again, they are not counted by the cache's budget.
+    pub fn preload(
+        &mut self,
+        columns: &HashSet<(i32, i32)>,
+        bottom: i32,
+        top: i32,
+    ) -> Result<(), mining::BlockError> {
+        let bottom = bottom.max(mining::WORLD_BOTTOM);
+        let top = top.min(mining::WORLD_TOP).max(bottom);
+        let mut sorted: Vec<(i32, i32)> = columns.iter().cloned().collect();
+        sorted.sort_by_key(|&(x, z)| (x.div_euclid(16), z.div_euclid(16), x, z));
+        self.columns = Columns::default();
+        let mut blocks = HashMap::new();
+        'columns: for (x, z) in sorted {
+            let mut column = Vec::with_capacity((top - bottom) as usize);
+            for y in bottom..top {
+                match self.get_block((x, y, z)) {
+                    Ok(block) => column.push(block),
+                    Err(mining::BlockError::MissingRegion(..)) => continue 'columns,
+                    Err(e) => return Err(e),
+                }
+            }
+            blocks.insert((x, z), column);
+        }
+        self.columns = Columns { bottom, top, blocks };
+        return Ok(());
+    }
+}
+
+/// Where the block at a set of absolute coordinates is stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockLocation {
+    /// Region coordinates of the region file.
+    pub region: (i32, i32),
+    /// Absolute chunk coordinates.
+    pub chunk: (i32, i32),
+    /// The x and z of the chunk within its region.
+    pub local_chunk: (usize, usize),
+    /// The 16 block tall section of the chunk, numbered from the bottom of the world.
+    pub section: usize,
+    /// Index of the block within its section, which is ordered by y, then z, then x.
+    pub index: usize,
+}
+
+impl BlockLocation {
+    /// Negative coordinates are rounded down so that x = -1 is the last block of chunk -1 and region -1 rather than the first block of chunk 0. Returns [OutOfBounds](`mining::BlockError::OutOfBounds`) above or below the height limits of the world.
+    pub fn of(coords: (i32, i32, i32)) -> Result<BlockLocation, mining::BlockError> {
+        let (x, y, z) = coords;
+        if !(mining::WORLD_BOTTOM..mining::WORLD_TOP).contains(&y) {
+            return Err(mining::BlockError::OutOfBounds(coords));
+        }
+        let chunk = (x.div_euclid(16), z.div_euclid(16));
+        let height = y - mining::WORLD_BOTTOM;
+        return Ok(BlockLocation {
+            region: (chunk.0.div_euclid(REGION_CHUNKS), chunk.1.div_euclid(REGION_CHUNKS)),
+            chunk,
+            local_chunk: (
+                chunk.0.rem_euclid(REGION_CHUNKS) as usize,
+                chunk.1.rem_euclid(REGION_CHUNKS) as usize,
+            ),
+            section: (height / 16) as usize,
+            index: (height % 16 * 256 + z.rem_euclid(16) * 16 + x.rem_euclid(16)) as usize,
+        });
+    }
+}
+
+/// The name of the file for the region at the region coordinates.
+pub fn region_file_name(x: i32, z: i32) -> String {
+    return format!("r.{}.{}.mca", x, z);
+}
+
+/// The region coordinates of a region file named `r.<x>.<z>.mca`.
+pub fn region_coords(file_name: &str) -> Option<(i32, i32)> {
+    let parts: Vec<&str> = file_name.split('.').collect();
+    if parts.len() != 4 || parts[0] != "r" || parts[3] != "mca" {
+        return None;
+    }
+    return Some((parts[1].parse().ok()?, parts[2].parse().ok()?));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::{BlockError, WORLD_BOTTOM, WORLD_TOP};
+
+    #[test]
+    fn negative_coordinates_round_down() {
+        let location = BlockLocation::of((-1, 0, -1)).unwrap();
+        assert_eq!(location.chunk, (-1, -1));
+        assert_eq!(location.region, (-1, -1));
+        assert_eq!(location.local_chunk, (31, 31));
+        assert_eq!(location.section, 4);
+        assert_eq!(location.index, 15 * 16 + 15);
+    }
+
+    #[test]
+    fn chunk_and_region_borders() {
+        let first = BlockLocation::of((-16, 0, 15)).unwrap();
+        assert_eq!(first.chunk, (-1, 0));
+        assert_eq!(first.local_chunk, (31, 0));
+        assert_eq!(first.index, 15 * 16);
+        let past = BlockLocation::of((-17, 0, 16)).unwrap();
+        assert_eq!(past.chunk, (-2, 1));
+        assert_eq!(past.local_chunk, (30, 1));
+        let region = BlockLocation::of((-512, 0, 511)).unwrap();
+        assert_eq!(region.region, (-1, 0));
+        assert_eq!(region.local_chunk, (0, 31));
+        let next = BlockLocation::of((-513, 0, 512)).unwrap();
+        assert_eq!(next.region, (-2, 1));
+        assert_eq!(next.local_chunk, (31, 0));
+    }
+
+    #[test]
+    fn sections_start_at_the_bottom_of_the_world() {
+        let bottom = BlockLocation::of((0, WORLD_BOTTOM, 0)).unwrap();
+        assert_eq!((bottom.section, bottom.index), (0, 0));
+        let below_zero = BlockLocation::of((0, -1, 0)).unwrap();
+        assert_eq!((below_zero.section, below_zero.index), (3, 15 * 256));
+        let top = BlockLocation::of((0, WORLD_TOP - 1, 0)).unwrap();
+        assert_eq!((top.section, top.index), (cache::CHUNK_SECTIONS - 1, 15 * 256));
+    }
+
+    #[test]
+    fn outside_of_the_world_height() {
+        assert_eq!(
+            BlockLocation::of((0, WORLD_BOTTOM - 1, 0)),
+            Err(BlockError::OutOfBounds((0, WORLD_BOTTOM - 1, 0)))
+        );
+        assert_eq!(
+            BlockLocation::of((0, WORLD_TOP, 0)),
+            Err(BlockError::OutOfBounds((0, WORLD_TOP, 0)))
+        );
+    }
+
+    #[test]
+    fn region_file_names() {
+        assert_eq!(region_file_name(-1, 2), "r.-1.2.mca");
+        assert_eq!(region_coords("r.-1.2.mca"), Some((-1, 2)));
+        assert_eq!(region_coords("r.1.mca"), None);
+        assert_eq!(region_coords("r.a.2.mca"), None);
+    }
+}
@@ -1,11 +1,9 @@
 use core::panic;
-use std::{collections::{HashMap, VecDeque}, time::Instant};
+use std::{collections::HashSet, error::Error, fmt};
 
-use mvp_anvil::region::Region;
+use crate::{block::PaletteId, error::SimulationError, CancelToken, World};
 
-use crate::CachingRegion;
-
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Direction {
     North,
     South,
@@ -13,9 +11,59 @@ pub enum Direction {
     West,
 }
 
+impl Direction {
+    /// Every direction, in clockwise order starting from north.
+    pub fn all() -> [Direction; 4] {
+        return [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+    }
+
+    /// Parses the name of a direction such as `south`, case is ignored.
+    pub fn parse(text: &str) -> Result<Direction, String> {
+        return Direction::all()
+            .iter()
+            .find(|d| d.name() == text.trim().to_lowercase())
+            .cloned()
+            .ok_or_else(|| format!("Unknown direction '{}'", text));
+    }
+
+    pub fn name(&self) -> String {
+        return format!("{:?}", self).to_lowercase();
+    }
+
+    /// The direction to the left of someone facing this direction.
+    pub fn left(&self) -> Direction {
+        match self {
+            Direction::North => Direction::West,
+            Direction::West => Direction::South,
+            Direction::South => Direction::East,
+            Direction::East => Direction::North,
+        }
+    }
+
+    /// The direction to the right of someone facing this direction.
+    pub fn right(&self) -> Direction {
+        return self.left().opposite();
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SimpleBlock {
-    pub block: String,
+    /// The block in the [palette](`crate::World::palette`) of the world it was read from.
+    pub block: PaletteId,
     pub x: i32,
     pub y: i32,
     pub z: i32,
@@ -31,7 +79,7 @@ impl PartialEq for SimpleBlock {
 }
 
 impl SimpleBlock {
-    pub fn new(coords: (i32, i32, i32), block: String) -> SimpleBlock {
+    pub fn new(coords: (i32, i32, i32), block: PaletteId) -> SimpleBlock {
         return SimpleBlock {
             block,
             x: coords.0,
@@ -45,6 +93,107 @@ impl SimpleBlock {
     }
 }
 
+/// The blocks that a technique mines, in the order that they are mined. Blocks are grouped into segments, each tunnel, branch, or poke hole is its own segment.
+///
+/// Techniques only describe what is mined, the blocks that are exposed are derived from this by [survey].
+#[derive(Clone, Debug, Default)]
+pub struct Excavation {
+    segments: Vec<Vec<(i32, i32, i32)>>,
+}
+
+impl Excavation {
+    pub fn new() -> Excavation {
+        return Excavation {
+            segments: Vec::new(),
+        };
+    }
+
+    /// Starts a new segment, the following blocks that are dug will be part of it.
+    pub fn segment(&mut self) {
+        if !matches!(self.segments.last(), Some(s) if s.is_empty()) {
+            self.segments.push(Vec::new());
+        }
+    }
+
+    /// Mines the block at `coords`. Blocks can be dug more than once, they are only counted once.
+    pub fn dig(&mut self, coords: (i32, i32, i32)) {
+        if self.segments.is_empty() {
+            self.segments.push(Vec::new());
+        }
+        self.segments.last_mut().unwrap().push(coords);
+    }
+
+    /// Combines the segments of another excavation into this one.
+    pub fn append(&mut self, mut other: Excavation) {
+        self.segments.append(&mut other.segments);
+    }
+
+    pub fn segments(&self) -> &[Vec<(i32, i32, i32)>] {
+        return &self.segments;
+    }
+
+    /// Every distinct block that is mined.
+    pub fn mined(&self) -> HashSet<(i32, i32, i32)> {
+        return self.segments.iter().flatten().cloned().collect();
+    }
+}
+
+/// The blocks that can be seen after an excavation, this is each mined block along with every block that shares a face with a mined block.
+#[derive(Clone, Debug, Default)]
+pub struct Survey {
+    /// Each block that is mined or exposed, a block only appears once.
+    pub blocks: Vec<SimpleBlock>,
+    /// Number of distinct blocks that were mined.
+    pub mined: u32,
+    /// Number of distinct blocks that were seen, this includes the mined blocks.
+    pub exposed: u32,
+}
+
+/// The six blocks that share a face with `coords`.
+pub fn face_neighbours(coords: (i32, i32, i32)) -> [(i32, i32, i32); 6] {
+    let (x, y, z) = coords;
+    return [
+        (x + 1, y, z),
+        (x - 1, y, z),
+        (x, y + 1, z),
+        (x, y - 1, z),
+        (x, y, z + 1),
+        (x, y, z - 1),
+    ];
+}
+
+/// Determines the exact set of blocks that are mined and exposed by an excavation and reads each of them from the world. Overlaps between corridors, branches, and poke holes are only counted once. Nothing above or below the height limits of the world can be mined or exposed, so blocks of the excavation past them are left out rather than failing the survey, such as the steps of a staircase that started near the bottom of the world.
+///
+/// `cancel` is checked before each segment, a cancelled survey returns [Cancelled](`SimulationError::Cancelled`).
+pub fn survey(
+    world: &mut World,
+    excavation: &Excavation,
+    cancel: &CancelToken,
+) -> Result<Survey, SimulationError> {
+    let mined = excavation.mined();
+    let mut seen = HashSet::new();
+    let mut results = Survey::default();
+    for segment in excavation.segments() {
+        cancel.check()?;
+        for coords in segment.iter().filter(|c| in_world(c.1)) {
+            if seen.insert(*coords) {
+                results.blocks.push(SimpleBlock::new(*coords, get_block(world, *coords)?));
+            }
+            for neighbour in face_neighbours(*coords).iter() {
+                if !seen.insert(*neighbour) {
+                    continue;
+                }
+                if let Some(block) = visible_block(world, *neighbour)? {
+                    results.blocks.push(SimpleBlock::new(*neighbour, block));
+                }
+            }
+        }
+    }
+    results.mined = mined.iter().filter(|c| in_world(c.1)).count() as u32;
+    results.exposed = results.blocks.len() as u32;
+    return Ok(results);
+}
+
 pub fn shift_coords(
     direction: &Direction,
     mut coords: (i32, i32, i32),
@@ -59,245 +208,331 @@ pub fn shift_coords(
     return coords;
 }
 
-/// Takes a region file and coordinates and returns the name of the block at that location. This handles the intermediary step of determining the x and z [chunks](`mvp_anvil::chunk::Chunk`) that correspond with the coordinates.
+/// Lowest y level of the world.
+pub const WORLD_BOTTOM: i32 = -64;
+/// The y level above the highest blocks of the world.
+pub const WORLD_TOP: i32 = 320;
+
+/// Reasons that a block can not be read from the world.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockError {
+    /// The coordinates are above or below the height limits of the world.
+    OutOfBounds((i32, i32, i32)),
+    /// The coordinates are in a region, given by its region coordinates, that has no region file.
+    MissingRegion((i32, i32, i32), i32, i32),
+    /// The path of a region file can not be given to mvp_anvil since it is not valid unicode.
+    RegionPath(String),
+    /// Every id of the [palette](`crate::block::Palette`) is in use, there are more distinct blocks than it can hold.
+    PaletteFull,
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockError::OutOfBounds((x, y, z)) => write!(
+                f,
+                "Block {}, {}, {} is outside of the world height of {} to {}",
+                x, y, z, WORLD_BOTTOM, WORLD_TOP
+            ),
+            BlockError::MissingRegion((x, y, z), region_x, region_z) => write!(
+                f,
+                "Block {}, {}, {} is in {} which is not in the regions directory",
+                x,
+                y,
+                z,
+                crate::region_file_name(*region_x, *region_z)
+            ),
+            BlockError::RegionPath(path) => write!(
+                f,
+                "The path of region file {} is not valid unicode",
+                path
+            ),
+            BlockError::PaletteFull => write!(
+                f,
+                "More than {} different blocks were read, there is no room left in the block palette",
+                crate::block::PaletteId::MAX as usize + 1
+            ),
+        }
+    }
+}
+
+impl Error for BlockError {}
+
+/// Takes the world and coordinates and returns the id of the block at that location in the [palette](`crate::World::palette`) of the world, the palette gives the block and its category. This handles the intermediary step of determining the x and z [chunks](`mvp_anvil::chunk::Chunk`) that correspond with the coordinates, negative coordinates are rounded down so that they land in the correct chunk.
 ///
-/// * `region` - The [region](`mvp_anvil::region::Region`) file that the block will be retrieved from.
+/// * `world` - The [world](`crate::World`) that the block will be retrieved from.
 /// * `coords` - The tuple of xyz coordinates of the block.
-pub fn get_block(region: &mut CachingRegion, coords: (i32, i32, i32)) -> String {
-    
-    let chunk_x = coords.0 / 16;
-    let chunk_z = coords.2 / 16;
-    // let t2 = Instant::now();
-    let chunk = region.get_chunk(chunk_x as usize, chunk_z as usize);
-    // println!("Getting chunk took {} milliseconds", t2.elapsed().as_millis());
-    let timer = Instant::now();
-    let block = chunk.get_block(coords.0 % 16, coords.1, coords.2 % 16).id;
-    // println!("Getting block took {} ns", timer.elapsed().as_nanos());
-    return block;
+pub fn get_block(world: &mut World, coords: (i32, i32, i32)) -> Result<PaletteId, BlockError> {
+    return world.get_block(coords);
 }
 
-fn two_by_one_single(
-    region: &mut CachingRegion,
-    direction: &Direction,
-    coords: (i32, i32, i32),
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut blocks = Vec::new();
-    let mut x_range = 0..1;
-    let mut z_range = 0..1;
-    match direction {
-        Direction::North | Direction::South => x_range = -1..2,
-        Direction::East | Direction::West => z_range = -1..2,
-    };
-    for x in x_range {
-        for y in 0..2 {
-            for z in z_range.clone() {
-                let new_coords = (coords.0 + x, coords.1 + y, coords.2 + z);
-                blocks.push(SimpleBlock::new(new_coords, get_block(region, new_coords)));
-            }
-        }
+/// Whether a y level is within the height limits of the world.
+fn in_world(y: i32) -> bool {
+    return (WORLD_BOTTOM..WORLD_TOP).contains(&y);
+}
+
+/// Like [get_block] except that blocks outside of the height limits are None, there is nothing there to be seen.
+fn visible_block(world: &mut World, coords: (i32, i32, i32)) -> Result<Option<PaletteId>, BlockError> {
+    match get_block(world, coords) {
+        Ok(block) => return Ok(Some(block)),
+        Err(BlockError::OutOfBounds(_)) => return Ok(None),
+        Err(e) => return Err(e),
     }
-    blocks.push(SimpleBlock::new(
-        (coords.0, coords.1 - 1, coords.2),
-        get_block(region, (coords.0, coords.1 - 1, coords.2)),
-    ));
-    blocks.push(SimpleBlock::new(
-        (coords.0, coords.1 + 2, coords.2),
-        get_block(region, (coords.0, coords.1 + 2, coords.2)),
-    ));
-    return (blocks, 2, 8);
 }
 
-pub fn two_by_one_length(
-    region: &mut CachingRegion,
-    direction: &Direction,
-    coords: (i32, i32, i32),
-    length: i32,
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut results = (Vec::new(), 0, 0);
-    for n in 0..length {
-        // let direction = direction.clone();
-        // let direction_dup = direction.clone();
-        let mut res = two_by_one_single(region, direction, shift_coords(direction, coords, n));
-        results.0.append(&mut res.0);
-        results.1 += res.1;
-        results.2 += res.2;
-    }
-    return results;
+/// Whether the block at the coordinates is ore that a player would see. Blocks in regions that have no file can't be seen, so veins stop at them rather than failing the simulation.
+fn is_ore(world: &mut World, coords: (i32, i32, i32)) -> Result<bool, BlockError> {
+    match visible_block(world, coords) {
+        Ok(block) => return Ok(block.is_some_and(|b| world.palette().is_ore(b))),
+        Err(BlockError::MissingRegion(_, _, _)) => return Ok(false),
+        Err(e) => return Err(e),
+    }
 }
 
-pub fn two_by_one_end(
-    region: &mut CachingRegion,
-    direction: &Direction,
-    coords: (i32, i32, i32),
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut results = (Vec::new(), 0, 2);
-    match direction {
-        Direction::North => {
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1, coords.2 - 1),
-                get_block(region, (coords.0, coords.1, coords.2 - 1)),
-            ));
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1 + 1, coords.2 - 1),
-                get_block(region, (coords.0, coords.1 + 1, coords.2 - 1)),
-            ));
-        }
-        Direction::South => {
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1, coords.2 + 1),
-                get_block(region, (coords.0, coords.1, coords.2 + 1)),
-            ));
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1 + 1, coords.2 + 1),
-                get_block(region, (coords.0, coords.1 + 1, coords.2 + 1)),
-            ));
+/// Mines out every ore vein that an excavation exposes the way that a player would once they see ore. Each ore block that shares a face with a mined block is mined along with every ore block connected to it, each vein is added as a new segment. Only blocks in the ore categories of the world's classification are followed, and veins are not followed into regions that have no file.
+pub fn follow_veins(world: &mut World, excavation: &mut Excavation) -> Result<(), BlockError> {
+    let mut mined = excavation.mined();
+    let mut checked = HashSet::new();
+    let candidates: Vec<(i32, i32, i32)> = excavation
+        .segments()
+        .iter()
+        .flatten()
+        .flat_map(|c| face_neighbours(*c).to_vec())
+        .collect();
+    for seed in candidates {
+        if mined.contains(&seed) || !checked.insert(seed) {
+            continue;
         }
-        Direction::East => {
-            results.0.push(SimpleBlock::new(
-                (coords.0 + 1, coords.1, coords.2),
-                get_block(region, (coords.0 + 1, coords.1, coords.2)),
-            ));
-            results.0.push(SimpleBlock::new(
-                (coords.0 + 1, coords.1 + 1, coords.2),
-                get_block(region, (coords.0 + 1, coords.1 + 1, coords.2)),
-            ));
+        if !is_ore(world, seed)? {
+            continue;
         }
-        Direction::West => {
-            results.0.push(SimpleBlock::new(
-                (coords.0 - 1, coords.1, coords.2),
-                get_block(region, (coords.0 - 1, coords.1, coords.2)),
-            ));
-            results.0.push(SimpleBlock::new(
-                (coords.0 - 1, coords.1 + 1, coords.2),
-                get_block(region, (coords.0 - 1, coords.1 + 1, coords.2)),
-            ));
+        // Flood fill the vein that the seed is part of
+        excavation.segment();
+        let mut vein = vec![seed];
+        while let Some(coords) = vein.pop() {
+            excavation.dig(coords);
+            mined.insert(coords);
+            for neighbour in face_neighbours(coords).iter() {
+                if !mined.contains(neighbour)
+                    && checked.insert(*neighbour)
+                    && is_ore(world, *neighbour)?
+                {
+                    vein.push(*neighbour);
+                }
+            }
         }
     }
-    return results;
+    return Ok(());
 }
 
-fn one_by_one_single(
-    region: &mut CachingRegion,
-    direction: &Direction,
-    coords: (i32, i32, i32),
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut blocks = Vec::new();
-    let mut x_range = 0..1;
-    let mut z_range = 0..1;
-    match direction {
-        Direction::North | Direction::South => x_range = -1..2,
-        Direction::East | Direction::West => z_range = -1..2,
-    };
-    for x in x_range {
-        for y in 0..1 {
-            let z_range = z_range.clone();
-            for z in z_range {
-                let new_coords = (coords.0 + x, coords.1 + y, coords.2 + z);
-                blocks.push(SimpleBlock::new(new_coords, get_block(region, new_coords)));
+/// Blocks that a careful player stops digging towards once they are exposed: lava, water, and the air of caves. These are in the [DEFAULT_NAMESPACE](`crate::block::DEFAULT_NAMESPACE`) and are hazards in any state.
+pub const HAZARDS: [&str; 6] = [
+    "lava",
+    "flowing_lava",
+    "water",
+    "flowing_water",
+    "air",
+    "cave_air",
+];
+
+/// Carries out an excavation the way a careful player would. Each segment is dug in order and abandoned as soon as a mined block exposes one of the [HAZARDS], the block that exposed it is still mined. Blocks that can no longer be reached from `start`, where the player stands, without going through an abandoned part are dropped as well, such as poke holes off of a branch that was cut short.
+///
+/// Returns the blocks that are actually mined along with the number of segments that were cut short or dropped.
+pub fn avoid_hazards(
+    world: &mut World,
+    excavation: &Excavation,
+    start: (i32, i32, i32),
+) -> Result<(Excavation, u32), BlockError> {
+    let mut mined = HashSet::new();
+    let mut truncated = Vec::new();
+    for segment in excavation.segments() {
+        let mut dug = Vec::new();
+        for coords in segment {
+            dug.push(*coords);
+            mined.insert(*coords);
+            let mut hazard = false;
+            for neighbour in face_neighbours(*coords).iter() {
+                if mined.contains(neighbour) {
+                    continue;
+                }
+                if let Some(block) = visible_block(world, *neighbour)? {
+                    hazard |= HAZARDS.iter().any(|h| world.palette().block(block).is(h));
+                }
+            }
+            if hazard {
+                break;
             }
         }
+        truncated.push(dug);
+    }
+
+    // Flood fill from the player's position to find what can still be reached
+    let head = (start.0, start.1 + 1, start.2);
+    mined.insert(start);
+    mined.insert(head);
+    let mut reachable = HashSet::new();
+    let mut frontier = vec![start, head];
+    while let Some(coords) = frontier.pop() {
+        if !mined.contains(&coords) || !reachable.insert(coords) {
+            continue;
+        }
+        frontier.extend_from_slice(&face_neighbours(coords));
     }
-    blocks.push(SimpleBlock::new(
-        (coords.0, coords.1 - 1, coords.2),
-        get_block(region, (coords.0, coords.1 - 1, coords.2)),
-    ));
-    blocks.push(SimpleBlock::new(
-        (coords.0, coords.1 + 1, coords.2),
-        get_block(region, (coords.0, coords.1 + 2, coords.2)),
-    ));
-    return (blocks, 1, 5);
+
+    let mut kept = Excavation::new();
+    let mut aborted = 0;
+    for (planned, dug) in excavation.segments().iter().zip(truncated) {
+        let before = dug.len();
+        let mut after = 0;
+        kept.segment();
+        for coords in dug.into_iter().filter(|c| reachable.contains(c)) {
+            kept.dig(coords);
+            after += 1;
+        }
+        if before < planned.len() || after < before {
+            aborted += 1;
+        }
+    }
+    return Ok((kept, aborted));
+}
+
+/// Mines a 2 tall slice of a tunnel, `coords` is the bottom block.
+fn two_by_one_single(excavation: &mut Excavation, coords: (i32, i32, i32)) {
+    excavation.dig(coords);
+    excavation.dig((coords.0, coords.1 + 1, coords.2));
 }
 
-fn one_by_one_length(
-    region: &mut CachingRegion,
+/// Mines a 2 tall 1 wide tunnel `length` blocks long starting at `coords` as a new segment.
+pub fn two_by_one_length(
+    excavation: &mut Excavation,
     direction: &Direction,
     coords: (i32, i32, i32),
     length: i32,
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut results = (Vec::new(), 0, 0);
+) {
+    excavation.segment();
     for n in 0..length {
-        let mut res = one_by_one_single(region, direction, shift_coords(direction, coords, n));
-        results.0.append(&mut res.0);
-        results.1 += res.1;
-        results.2 += res.2;
+        two_by_one_single(excavation, shift_coords(direction, coords, n));
+    }
+}
+
+/// Mines a 1x1 poke hole `depth` blocks deep starting at `coords` as a new segment.
+pub fn poke(excavation: &mut Excavation, direction: &Direction, coords: (i32, i32, i32), depth: i32) {
+    if depth < 1 {
+        panic!("Poke should be at least 1 block in depth")
+    }
+    excavation.segment();
+    for n in 0..depth {
+        excavation.dig(shift_coords(direction, coords, n));
     }
-    return results;
 }
 
-fn one_by_one_end(
-    region: &mut CachingRegion,
+/// Mines a section of the main corridor between two pairs of branches as a new segment. `coords` is the slice of the corridor that lines up with the first pair and the section ends at the slice that lines up with the next pair, `branch_spacing` blocks away.
+pub fn expand_corridor(
+    excavation: &mut Excavation,
     direction: &Direction,
+    branch_spacing: i32,
     coords: (i32, i32, i32),
-) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut results = (Vec::new(), 0, 1);
-    match direction {
-        Direction::North => {
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1, coords.2 - 1),
-                get_block(region, (coords.0, coords.1, coords.2 - 1)),
-            ));
-        }
-        Direction::South => {
-            results.0.push(SimpleBlock::new(
-                (coords.0, coords.1, coords.2 + 1),
-                get_block(region, (coords.0, coords.1, coords.2 + 1)),
-            ));
+) {
+    two_by_one_length(excavation, direction, coords, branch_spacing + 1);
+}
+
+/// Mines a tunnel `width` wide and `height` tall that is `length` blocks long as a new segment. `coords` is the bottom block of the first slice, the tunnel is centred on it with any extra width going to the right.
+pub fn tunnel(
+    excavation: &mut Excavation,
+    direction: &Direction,
+    coords: (i32, i32, i32),
+    width: i32,
+    height: i32,
+    length: i32,
+) {
+    excavation.segment();
+    let right = direction.right();
+    for n in 0..length {
+        let slice = shift_coords(direction, coords, n);
+        for w in -(width - 1) / 2..=width / 2 {
+            let column = shift_coords(&right, slice, w);
+            for h in 0..height {
+                excavation.dig((column.0, column.1 + h, column.2));
+            }
         }
-        Direction::East => {
-            results.0.push(SimpleBlock::new(
-                (coords.0 + 1, coords.1, coords.2),
-                get_block(region, (coords.0 + 1, coords.1, coords.2)),
-            ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{in_world, survey, two_by_one_length, Direction, Excavation, WORLD_BOTTOM, WORLD_TOP};
+    use crate::{CancelToken, World, cache::ChunkCache, classification::Classification, techniques::{quarry, tunnel, MiningTechnique}};
+
+    /// A world of stone around every column that the excavation reaches into.
+    fn stone_around(excavation: &Excavation) -> World {
+        let classification = Classification::parse("count stone\nstone:stone").unwrap();
+        let cache = Arc::new(ChunkCache::new(&classification, 1));
+        let mined = excavation.mined();
+        let x = mined.iter().map(|c| c.0).min().unwrap() - 1..mined.iter().map(|c| c.0).max().unwrap() + 2;
+        let z = mined.iter().map(|c| c.2).min().unwrap() - 1..mined.iter().map(|c| c.2).max().unwrap() + 2;
+        return World::generated(&cache, x, z, |_| "stone");
+    }
+
+    #[test]
+    fn techniques_stop_at_the_height_limits() {
+        let techniques: Vec<Box<dyn MiningTechnique>> = vec![
+            Box::new(tunnel::Staircase),
+            Box::new(quarry::Quarry),
+            Box::new(tunnel::Tunnel { width: 1, height: 2 }),
+        ];
+        for technique in &techniques {
+            for y in [WORLD_BOTTOM, WORLD_TOP - 1].iter() {
+                let excavation = technique.run((0, *y, 0), &Direction::South, &technique.default_parameters()).unwrap();
+                let mut world = stone_around(&excavation);
+                let results = survey(&mut world, &excavation, &CancelToken::new())
+                    .unwrap_or_else(|e| panic!("{} at y {}: {}", technique.name(), y, e));
+                let inside = excavation.mined().iter().filter(|c| in_world(c.1)).count();
+                assert!(inside > 0, "{} at y {}", technique.name(), y);
+                assert_eq!(results.mined as usize, inside, "{} at y {}", technique.name(), y);
+                assert!(results.blocks.iter().all(|b| in_world(b.y)));
+            }
         }
-        Direction::West => {
-            results.0.push(SimpleBlock::new(
-                (coords.0 - 1, coords.1, coords.2),
-                get_block(region, (coords.0 - 1, coords.1, coords.2)),
-            ));
+    }
+
+    /// Stone with iron beside the start of a tunnel heading south from the origin, diamonds above it, and iron that the tunnel does not reach.
+    fn ores(coords: (i32, i32, i32)) -> &'static str {
+        match coords {
+            (1, 0, 0) | (5, 0, 0) => return "iron_ore",
+            (0, 2, 0) => return "diamond_ore",
+            _ => return "stone",
         }
     }
-    return results;
-}
 
-fn poke_start(region: &mut CachingRegion, coords: (i32, i32, i32)) -> (Vec<SimpleBlock>, u32, u32) {
-    let mut results = (Vec::new(), 0, 1);
-    results.0.push(SimpleBlock::new(
-        (coords.0, coords.1 + 1, coords.2),
-        get_block(region, (coords.0, coords.1 + 1, coords.2)),
-    ));
-    return results;
-}
+    fn ore_world() -> World {
+        let classification = Classification::parse("ore iron\nore diamonds\niron_ore:iron\ndiamond_ore:diamonds").unwrap();
+        let cache = Arc::new(ChunkCache::new(&classification, 1));
+        return World::generated(&cache, -8..8, -8..8, ores);
+    }
 
-pub fn poke(
-    region: &mut CachingRegion,
-    direction: &Direction,
-    coords: (i32, i32, i32),
-    depth: i32,
-) -> (Vec<SimpleBlock>, u32, u32) {
-    if depth < 1 {
-        panic!("Poke should be at least 1 block in depth")
+    #[test]
+    fn tunnel_mines_and_exposes_known_blocks() {
+        let mut world = ore_world();
+        let mut excavation = Excavation::new();
+        two_by_one_length(&mut excavation, &Direction::South, (0, 0, 0), 1);
+        let results = survey(&mut world, &excavation, &CancelToken::new()).unwrap();
+        assert_eq!(results.mined, 2);
+        // Both mined blocks along with the 5 faces of each that are not shared
+        assert_eq!(results.exposed, 12);
+        let counts = world.palette().count(results.blocks.iter().map(|b| b.block));
+        assert_eq!(counts, vec![1, 1]);
+    }
+
+    #[test]
+    fn overlapping_segments_are_counted_once() {
+        let mut world = ore_world();
+        let mut excavation = Excavation::new();
+        two_by_one_length(&mut excavation, &Direction::South, (0, 0, 0), 2);
+        two_by_one_length(&mut excavation, &Direction::South, (0, 0, 0), 2);
+        let results = survey(&mut world, &excavation, &CancelToken::new()).unwrap();
+        assert_eq!(results.mined, 4);
+        // A 1x2x2 hole has 16 faces
+        assert_eq!(results.exposed, 4 + 16);
     }
-    let mut results = (Vec::new(), 0, 0);
-    let mut res = poke_start(region, coords);
-    results.0.append(&mut res.0);
-    results.1 += res.1;
-    results.2 += res.2;
-    let mut res = one_by_one_length(
-        region,
-        direction,
-        shift_coords(direction, coords, 1),
-        depth - 1,
-    );
-    results.0.append(&mut res.0);
-    results.1 += res.1;
-    results.2 += res.2;
-    let mut res = one_by_one_end(
-        region,
-        direction,
-        shift_coords(direction, coords, depth - 1),
-    );
-    results.0.append(&mut res.0);
-    results.1 += res.1;
-    results.2 += res.2;
-    return results;
 }
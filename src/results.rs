@@ -0,0 +1,200 @@
+use std::{
+    fs::File,
+    io,
+    process,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    classification::Classification,
+    error::SimulationError,
+    simulations::SimulationContext,
+    sinks::{ResultRow, ResultSink, Sink},
+    techniques::{parameters_text, Parameters},
+    ui::RunSummary,
+};
+
+/// Version of the metadata and long format results, increased whenever a field or column changes meaning.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Recorded as the version of Minecraft that generated the region files when it was not given with `--game-version`. mvp_anvil does not read the data version of chunks so it can not be found from the region files themselves.
+pub const UNKNOWN_GAME_VERSION: &str = "unknown";
+
+/// Columns of the long format results, every row is a single metric of a single simulation.
+pub const LONG_COLUMNS: [&str; 7] = ["run_id", "region", "technique", "params", "y", "metric", "value"];
+
+/// Identifies a single run of the program, made from the time it started and the process id so that runs started in the same second do not collide.
+pub fn new_run_id() -> String {
+    return format!("{}-{}", now(), process::id());
+}
+
+/// Seconds since the unix epoch.
+fn now() -> u64 {
+    return SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+}
+
+/// Everything needed to reproduce a run or to tell whether the results of two runs can be compared, written as `run-<run_id>.json` beside the result files.
+#[derive(Debug, Serialize)]
+pub struct RunMetadata {
+    pub schema_version: u32,
+    pub run_id: String,
+    pub program_version: String,
+    /// Version of Minecraft that generated the region files as given by the user, or [UNKNOWN_GAME_VERSION].
+    pub game_version: String,
+    /// The kind of simulation, such as `range` or `chunks`.
+    pub mode: String,
+    /// Seconds since the unix epoch.
+    pub started: u64,
+    /// Seconds since the unix epoch, not set until every simulation has stopped.
+    pub finished: Option<u64>,
+    pub cancelled: bool,
+    pub resumed: bool,
+    pub threads: u32,
+    pub y_min: i32,
+    pub y_max: i32,
+    pub regions: Vec<String>,
+    pub techniques: Vec<TechniqueMetadata>,
+    pub options: OptionsMetadata,
+    /// The categories, their weights, and the block in each category that were counted.
+    pub classification: Classification,
+    /// Format of the long format results, see [Sink].
+    pub sink: String,
+    /// Path of the long format results of this run.
+    pub results: String,
+}
+
+/// A technique along with every set of parameters that it is simulated with.
+#[derive(Debug, Serialize)]
+pub struct TechniqueMetadata {
+    pub name: String,
+    pub parameters: Vec<Parameters>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OptionsMetadata {
+    pub follow_veins: bool,
+    pub avoid_hazards: bool,
+    pub direction: String,
+    /// In the form accepted by `--sampling`, this includes the seed of random sampling.
+    pub sampling: String,
+    /// The x and z coordinates within each region of every start position.
+    pub starts: Vec<(i32, i32)>,
+}
+
+impl RunMetadata {
+    /// Metadata for a run that is starting now, `regions` and `techniques` are everything that the run simulates.
+    pub fn new(
+        run_id: &str,
+        mode: &str,
+        directory: &str,
+        context: &SimulationContext,
+        summary: &RunSummary,
+        regions: Vec<String>,
+        techniques: Vec<TechniqueMetadata>,
+    ) -> Result<RunMetadata, SimulationError> {
+        let options = &context.options;
+        return Ok(RunMetadata {
+            schema_version: SCHEMA_VERSION,
+            run_id: run_id.to_string(),
+            program_version: env!("CARGO_PKG_VERSION").to_string(),
+            game_version: options
+                .game_version
+                .clone()
+                .unwrap_or_else(|| String::from(UNKNOWN_GAME_VERSION)),
+            mode: mode.to_string(),
+            started: now(),
+            finished: None,
+            cancelled: false,
+            resumed: options.resume,
+            threads: summary.threads,
+            y_min: summary.y_range.0,
+            y_max: summary.y_range.1,
+            regions,
+            techniques,
+            options: OptionsMetadata {
+                follow_veins: options.follow_veins,
+                avoid_hazards: options.avoid_hazards,
+                direction: options.direction.name(),
+                sampling: options.sampling.text(),
+                starts: options.starts(),
+            },
+            classification: (*context.classification).clone(),
+            sink: options.sink.name(),
+            results: options.sink.path(directory, run_id),
+        });
+    }
+
+    /// Marks the run as over, it should be written again afterwards.
+    pub fn finish(&mut self, cancelled: bool) {
+        self.finished = Some(now());
+        self.cancelled = cancelled;
+    }
+
+    /// Writes the metadata to `run-<run_id>.json` in the directory, replacing an earlier copy.
+    pub fn write(&self, directory: &str) -> Result<(), SimulationError> {
+        let path = format!("{}/run-{}.json", directory, self.run_id);
+        let file = File::create(&path).map_err(SimulationError::io(&path))?;
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|e| SimulationError::Io(path.clone(), io::Error::from(e)))?;
+        return Ok(());
+    }
+}
+
+/// Long format results of a run shared by every simulation in it. Unlike the per region result files the columns never change with the technique, so the results of different runs can be joined on their metadata.
+pub struct RunResults {
+    run_id: String,
+    sink: Mutex<Box<dyn ResultSink>>,
+}
+
+impl RunResults {
+    /// Opens the sink that the results of the run are written to in the directory.
+    pub fn create(directory: &str, run_id: &str, sink: &Sink) -> Result<RunResults, SimulationError> {
+        return Ok(RunResults {
+            run_id: run_id.to_string(),
+            sink: Mutex::new(sink.open(directory, run_id)?),
+        });
+    }
+
+    pub fn run_id(&self) -> &str {
+        return &self.run_id;
+    }
+
+    /// Writes a row for each metric of a simulation, they are buffered until [flush](`RunResults::flush`) is called.
+    pub fn write<S: AsRef<str>>(
+        &self,
+        region: &str,
+        technique: &str,
+        parameters: &Parameters,
+        y: i32,
+        metrics: &[(S, f64)],
+    ) -> Result<(), SimulationError> {
+        let parameters = parameters_text(parameters);
+        let mut sink = self.sink.lock().unwrap();
+        for (metric, value) in metrics {
+            sink.write(&ResultRow {
+                run_id: &self.run_id,
+                region,
+                technique,
+                params: &parameters,
+                y,
+                metric: metric.as_ref(),
+                value: *value,
+            })?;
+        }
+        return Ok(());
+    }
+
+    pub fn flush(&self) -> Result<(), SimulationError> {
+        return self.sink.lock().unwrap().flush();
+    }
+
+    /// Stores the metadata in the sink as well, for sinks such as sqlite that keep every run together.
+    pub fn write_metadata(&self, metadata: &RunMetadata) -> Result<(), SimulationError> {
+        return self.sink.lock().unwrap().write_metadata(metadata);
+    }
+}
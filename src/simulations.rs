@@ -1,274 +1,821 @@
 use std::{
-    collections::HashMap,
-    fs::{self, File},
-    io::{BufRead, BufReader},
-    sync::mpsc::Sender,
+    collections::{HashMap, HashSet},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc::Sender, Arc},
     time::Instant,
 };
 
 use mvp_anvil::region::Region;
 
-use crate::{CachingRegion, ProgramStatus, mining::Direction, techniques::{self, branch_mining, branch_mining_with_poke_holes, chunks, Technique}};
+use crate::{CancelToken, ProgramStatus, World, block::Palette, cache::ChunkCache, classification::Classification, checkpoint::{result_writer, Checkpoint}, error::SimulationError, region_coords, results::RunResults, sinks::Sink, mining::{avoid_hazards, face_neighbours, follow_veins, survey, Direction, Excavation}, techniques::{self, parameters_text, ParameterSweep, Parameters, Technique}};
 
+/// Columns written for every simulated y level of a mining technique, in the order they appear in the result files. There is a column for each category of the classification between the block counts and the rest.
+pub fn mining_columns(classification: &Classification) -> Vec<String> {
+    let mut columns = vec![String::from("blocks mined"), String::from("blocks exposed")];
+    columns.extend(classification.categories().iter().map(|c| c.name.clone()));
+    columns.push(String::from("value"));
+    columns.push(String::from("aborted segments"));
+    columns.push(String::from("ores lost"));
+    return columns;
+}
+
+/// The options and columns that change what is written to the result files, recorded in the [checkpoint](`crate::checkpoint::Checkpoint`) so that a run is only resumed with the same ones.
+pub fn checkpoint_settings(options: &SimulationOptions, classification: &Classification) -> String {
+    return format!(
+        "start={},{};direction={};sampling={};reach={},{},{},{};follow_veins={};avoid_hazards={};columns={}",
+        options.start.0,
+        options.start.1,
+        options.direction.name(),
+        options.sampling.text(),
+        options.reach.x.0,
+        options.reach.x.1,
+        options.reach.z.0,
+        options.reach.z.1,
+        options.follow_veins,
+        options.avoid_hazards,
+        mining_columns(classification).join("|")
+    );
+}
+
+/// Width of a region in blocks, start positions are chosen from within a single region.
+const REGION_WIDTH: i32 = 512;
+
+/// Choices that change how a simulation is carried out, these apply to every technique in a simulation.
+#[derive(Clone, Debug)]
+pub struct SimulationOptions {
+    /// Mine out any ore veins that are exposed, see [follow_veins](`crate::mining::follow_veins`).
+    pub follow_veins: bool,
+    /// Abandon tunnels that expose lava, water, or caves, see [avoid_hazards](`crate::mining::avoid_hazards`).
+    pub avoid_hazards: bool,
+    /// The x and z coordinates within the region that the technique starts from when [sampling](`SimulationOptions::sampling`) is fixed.
+    pub start: (i32, i32),
+    /// The direction that the technique heads in, such as the direction of the main corridor.
+    pub direction: Direction,
+    pub sampling: Sampling,
+    /// Skip the work recorded in the [checkpoint](`crate::checkpoint::Checkpoint`) of a previous run and append to its result files.
+    pub resume: bool,
+    /// Where the long format results of the run are written.
+    pub sink: Sink,
+    /// Memory budget in MiB of the [chunk cache](`crate::cache::ChunkCache`) shared by every simulation of the run.
+    pub cache_budget: usize,
+    /// Simulate every y level and technique of a region in one pass, see [simulate_region].
+    pub single_pass: bool,
+    /// How far the techniques of the run reach from their starts, random and grid starts are kept far enough from the edges of the region for every technique to stay inside of it.
+    pub reach: Reach,
+    /// Version of Minecraft that generated the region files, only recorded in the [run metadata](`crate::results::RunMetadata`).
+    pub game_version: Option<String>,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> SimulationOptions {
+        return SimulationOptions {
+            follow_veins: false,
+            avoid_hazards: false,
+            start: (255, 255),
+            direction: Direction::South,
+            sampling: Sampling::Fixed,
+            resume: false,
+            sink: Sink::Csv,
+            cache_budget: 1024,
+            single_pass: false,
+            reach: Reach::default(),
+            game_version: None,
+        };
+    }
+}
+
+impl SimulationOptions {
+    /// The x and z coordinates within the region of every starting position that is simulated. Random and grid starts are only placed where every technique stays within the region, see [reach](`SimulationOptions::reach`).
+    pub fn starts(&self) -> Vec<(i32, i32)> {
+        let (x, z) = (Reach::start_bounds(self.reach.x), Reach::start_bounds(self.reach.z));
+        match self.sampling {
+            Sampling::Fixed => return vec![self.start],
+            Sampling::Random(count, seed) => {
+                // xorshift, the state can never be zero
+                let mut state = seed.max(1);
+                let mut next = |(low, high): (i32, i32)| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    return low + (state % (high - low + 1) as u64) as i32;
+                };
+                return (0..count).map(|_| (next(x), next(z))).collect();
+            }
+            Sampling::Grid(count) => {
+                let side = (count as f64).sqrt().ceil().max(1.0) as i32;
+                let spacing = |(low, high): (i32, i32)| (high - low + 1) / side;
+                return (0..count as i32)
+                    .map(|n| {
+                        (
+                            x.0 + spacing(x) / 2 + (n % side) * spacing(x),
+                            z.0 + spacing(z) / 2 + (n / side) * spacing(z),
+                        )
+                    })
+                    .collect();
+            }
+        }
+    }
+}
+
+/// Parses a start in the form `x,z`, the coordinates are within the region so both should be from 0 to 511.
+pub fn parse_start(text: &str) -> Result<(i32, i32), String> {
+    let (x, z) = text
+        .split_once(',')
+        .ok_or_else(|| format!("Start '{}' should be in the form x,z", text))?;
+    let parse = |t: &str| match t.trim().parse::<i32>() {
+        Ok(n) if (0..REGION_WIDTH).contains(&n) => Ok(n),
+        Ok(_) => Err(format!(
+            "Start '{}' should be within the region, from 0 to {}",
+            text,
+            REGION_WIDTH - 1
+        )),
+        Err(_) => Err(format!("Failed to parse start '{}'", text)),
+    };
+    return Ok((parse(x)?, parse(z)?));
+}
+
+/// How far along x and z a technique reaches from its start, counting the blocks beside those that it mines. Each is the lowest and highest offset from the start.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Reach {
+    pub x: (i32, i32),
+    pub z: (i32, i32),
+}
+
+impl Reach {
+    /// The reach of a technique with a set of parameters, found by laying it out without reading any blocks.
+    pub fn of(
+        technique: &Technique,
+        parameters: &Parameters,
+        direction: &Direction,
+    ) -> Result<Reach, SimulationError> {
+        let mut reach = Reach::default();
+        for (x, _, z) in layout(technique, (0, 0, 0), direction, parameters)?.mined() {
+            reach.x = (reach.x.0.min(x - 1), reach.x.1.max(x + 1));
+            reach.z = (reach.z.0.min(z - 1), reach.z.1.max(z + 1));
+        }
+        return Ok(reach);
+    }
+
+    /// The reach of both, for choosing starts that work for every technique of a run.
+    pub fn union(self, other: Reach) -> Reach {
+        return Reach {
+            x: (self.x.0.min(other.x.0), self.x.1.max(other.x.1)),
+            z: (self.z.0.min(other.z.0), self.z.1.max(other.z.1)),
+        };
+    }
+
+    /// The lowest and highest coordinate within the region along an axis that a start can be placed at so that the reach along it stays in the region. The whole region when the reach is wider than the region.
+    fn start_bounds((below, above): (i32, i32)) -> (i32, i32) {
+        let (low, high) = (-below, REGION_WIDTH - 1 - above);
+        if low > high {
+            return (0, REGION_WIDTH - 1);
+        }
+        return (low, high);
+    }
+}
+
+/// How the starting positions of a simulation are chosen. When more than one position is used the results of each are averaged.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sampling {
+    /// Only the [start](`SimulationOptions::start`) of the options.
+    Fixed,
+    /// A number of positions picked at random across the region from a seed, the same seed and techniques always give the same positions.
+    Random(u32, u64),
+    /// A number of positions spread evenly across the region.
+    Grid(u32),
+}
+
+impl Sampling {
+    /// Parses sampling in the form `fixed`, `random:<count>`, `random:<count>:<seed>`, or `grid:<count>`.
+    pub fn parse(text: &str) -> Result<Sampling, String> {
+        let parts: Vec<&str> = text.trim().split(':').collect();
+        let count = |t: Option<&&str>| match t.map(|t| t.parse::<u32>()) {
+            Some(Ok(count)) if count > 0 => Ok(count),
+            _ => Err(format!("Sampling '{}' needs a count greater than 0", text)),
+        };
+        match parts[0] {
+            "fixed" if parts.len() == 1 => return Ok(Sampling::Fixed),
+            "random" if parts.len() <= 3 => {
+                let seed = match parts.get(2) {
+                    Some(seed) => seed
+                        .parse::<u64>()
+                        .map_err(|_| format!("Failed to parse seed '{}'", seed))?,
+                    None => 1,
+                };
+                return Ok(Sampling::Random(count(parts.get(1))?, seed));
+            }
+            "grid" if parts.len() == 2 => return Ok(Sampling::Grid(count(parts.get(1))?)),
+            _ => {
+                return Err(format!(
+                    "Sampling '{}' should be fixed, random:<count>[:<seed>], or grid:<count>",
+                    text
+                ))
+            }
+        }
+    }
+
+    /// The sampling in the form that [parse](`Sampling::parse`) accepts.
+    pub fn text(&self) -> String {
+        match self {
+            Sampling::Fixed => return String::from("fixed"),
+            Sampling::Random(count, seed) => return format!("random:{}:{}", count, seed),
+            Sampling::Grid(count) => return format!("grid:{}", count),
+        }
+    }
+}
+
+/// Everything that the simulations of a run share. Each worker thread is given its own copy, which is cheap since everything other than the options is shared.
+#[derive(Clone)]
+pub struct SimulationContext {
+    pub options: SimulationOptions,
+    /// Read once so that every simulation of the run counts blocks the same way.
+    pub classification: Arc<Classification>,
+    pub cache: Arc<ChunkCache>,
+    pub checkpoint: Arc<Checkpoint>,
+    /// The long format results of the run.
+    pub run: Arc<RunResults>,
+    pub cancel: CancelToken,
+    /// Where status updates for the monitor are sent.
+    pub sender: Sender<ProgramStatus>,
+}
+
+/// A csv result file of a region and technique that rows are written to as each y level finishes.
+struct ResultFile {
+    path: String,
+    writer: csv::Writer<fs::File>,
+    region: String,
+    technique: String,
+    /// Parameters whose values lead each row, empty unless parameters are being swept.
+    names: Vec<String>,
+    columns: Vec<String>,
+}
+
+impl ResultFile {
+    /// Opens `mining_data/result-<region>-<technique>.csv`, or `mining_data/parameters-<region>-<technique>.csv` with the value of each parameter leading the row when `swept` is set.
+    fn open(
+        context: &SimulationContext,
+        region_file_name: &str,
+        technique: &Technique,
+        swept: bool,
+    ) -> Result<ResultFile, SimulationError> {
+        let (kind, names) = if swept {
+            let names = technique.parameters().iter().map(|p| p.name.to_string()).collect();
+            ("parameters", names)
+        } else {
+            ("result", Vec::new())
+        };
+        let path = format!("mining_data/{}-{}-{}.csv", kind, region_file_name, technique.name());
+        let columns = mining_columns(&context.classification);
+        let mut header: Vec<String> = names.clone();
+        header.push(String::from("y"));
+        header.extend(columns.iter().cloned());
+        let defaults = technique.default_parameters();
+        let finished = |row: &csv::StringRecord| {
+            let mut parameters = defaults.clone();
+            for (n, name) in names.iter().enumerate() {
+                match row.get(n).and_then(|v| v.parse::<i32>().ok()) {
+                    Some(value) => parameters.insert(name.clone(), value),
+                    None => return false,
+                };
+            }
+            return match row.get(names.len()).and_then(|y| y.parse::<i32>().ok()) {
+                Some(y) => context
+                    .checkpoint
+                    .is_done(region_file_name, &technique.name(), &parameters, y),
+                None => false,
+            };
+        };
+        let writer = result_writer(&path, &header, context.checkpoint.resuming(), finished)?;
+        return Ok(ResultFile {
+            path,
+            writer,
+            region: region_file_name.to_string(),
+            technique: technique.name(),
+            names,
+            columns,
+        });
+    }
+
+    /// Writes a row to the file and to the long format results of the run, and once both are flushed records it in the checkpoint.
+    fn write(
+        &mut self,
+        context: &SimulationContext,
+        parameters: &Parameters,
+        y: i32,
+        results: &HashMap<String, f64>,
+    ) -> Result<(), SimulationError> {
+        let mut record: Vec<String> = self.names.iter().map(|n| parameters[n].to_string()).collect();
+        record.push(y.to_string());
+        record.extend(self.columns.iter().map(|c| results[c].to_string()));
+        self.writer.write_record(&record)?;
+        self.writer.flush().map_err(SimulationError::io(&self.path))?;
+        context.run.write(
+            &self.region,
+            &self.technique,
+            parameters,
+            y,
+            &mining_metrics(results, &self.columns),
+        )?;
+        context.run.flush()?;
+        context.checkpoint.record(&self.region, &self.technique, parameters, y)?;
+        return Ok(());
+    }
+}
+
+/// Simulates each y level in the range and writes a row for each to `mining_data/result-<region>-<technique>.csv` as well as to the long format results of the run. Rows are flushed and recorded in the checkpoint as soon as they finish, y levels that the checkpoint already has are skipped.
 pub fn simulate_range(
+    context: &SimulationContext,
     region_file_name: String,
     technique: &Technique,
     max: i32,
     min: i32,
     id: u32,
-    sender: Sender<ProgramStatus>,
-) {
-    let f_name = region_file_name.clone();
-    let t = technique.clone();
-    let t1 = technique.clone();
-    let t2 = technique.clone();
-    fs::remove_file(format!(
-        "mining_data/result-{}-{}.csv",
-        region_file_name,
-        t1.name()
-    ))
-    .ok();
-    File::create(format!(
-        "mining_data/result-{}-{}.csv",
-        region_file_name,
-        t2.name()
-    ))
-    .unwrap();
-    let mut csv_writer = csv::Writer::from_path(format!(
-        "mining_data/result-{}-{}.csv",
-        region_file_name,
-        technique.clone().name()
-    ))
-    .unwrap();
-    csv_writer
-        .write_record(&[
-            "y",
-            "blocks mined",
-            "blocks exposed",
-            "lava",
-            "coal",
-            "copper",
-            "iron",
-            "lapis",
-            "redstone",
-            "gold",
-            "emeralds",
-            "diamonds",
-        ])
-        .unwrap();
+) -> Result<(), SimulationError> {
+    context.cancel.check()?;
+    let mut file = ResultFile::open(context, &region_file_name, technique, false)?;
+    let parameters = technique.default_parameters();
+    let total = (max - min).max(0) as u64;
+    for (done, y) in (min..max).enumerate() {
+        context.sender.send(ProgramStatus::ProgressSim(id, done as u64, total))?;
+        if context.checkpoint.is_done(&region_file_name, &technique.name(), &parameters, y) {
+            continue;
+        }
+        context.cancel.check()?;
+        let results = simulate(
+            context,
+            region_file_name.clone(),
+            technique,
+            &parameters,
+            y,
+            id,
+        )?;
+        context.sender.send(ProgramStatus::FinishSim(id))?;
+        file.write(context, &parameters, y, &results)?;
+    }
+    return Ok(());
+}
+
+/// Simulates every combination of parameters in the sweep for each y level in the range. Each combination and y level is written as a single row to `mining_data/parameters-<region>-<technique>.csv` with the parameter values leading the row. Like [simulate_range] rows are checkpointed as they finish.
+pub fn simulate_parameters(
+    context: &SimulationContext,
+    region_file_name: String,
+    technique: &Technique,
+    sweep: &ParameterSweep,
+    max: i32,
+    min: i32,
+    id: u32,
+) -> Result<(), SimulationError> {
+    context.cancel.check()?;
+    let combinations = sweep
+        .combinations(technique.as_ref())
+        .map_err(SimulationError::Parameters)?;
+    let mut file = ResultFile::open(context, &region_file_name, technique, true)?;
+    let total = combinations.len() as u64 * (max - min).max(0) as u64;
+    let mut done = 0;
+    for parameters in combinations {
+        for y in min..max {
+            context.sender.send(ProgramStatus::ProgressSim(id, done, total))?;
+            done += 1;
+            if context.checkpoint.is_done(&region_file_name, &technique.name(), &parameters, y) {
+                continue;
+            }
+            context.cancel.check()?;
+            let results = simulate(
+                context,
+                region_file_name.clone(),
+                technique,
+                &parameters,
+                y,
+                id,
+            )?;
+            context.sender.send(ProgramStatus::FinishSim(id))?;
+            file.write(context, &parameters, y, &results)?;
+        }
+    }
+    return Ok(());
+}
+
+/// Simulates every technique at each y level in the range in a single pass over the region. When a sweep is given every combination of its parameters is simulated like [simulate_parameters], otherwise each technique is simulated with its defaults like [simulate_range]. The rows written and checkpointed are the same either way.
+///
+/// Before simulating, each technique is run from every start without reading any blocks to find the columns that it mines and the ones beside them, and how far above and below its start it reaches. The blocks of those columns that the y range can reach are [preloaded](`World::preload`), so each chunk is read from its region once for the whole pass. Only vein following reads outside of them. The preloaded blocks take two bytes each on top of the chunk cache's budget, which comes to a few MiB for each start of a technique.
+pub fn simulate_region(
+    context: &SimulationContext,
+    region_file_name: String,
+    techniques: &[Technique],
+    sweep: Option<&ParameterSweep>,
+    max: i32,
+    min: i32,
+    id: u32,
+) -> Result<(), SimulationError> {
+    context.cancel.check()?;
+    let mut jobs = Vec::new();
+    for technique in techniques {
+        let sets = match sweep {
+            Some(sweep) => sweep
+                .combinations(technique.as_ref())
+                .map_err(SimulationError::Parameters)?,
+            None => vec![technique.default_parameters()],
+        };
+        let file = ResultFile::open(context, &region_file_name, technique, sweep.is_some())?;
+        jobs.push((technique, sets, file));
+    }
+
+    let starts = absolute_starts(context, &region_file_name)?;
+    let mut columns = HashSet::new();
+    let (mut below, mut above) = (0, 0);
+    for (technique, sets, _) in &jobs {
+        for parameters in sets {
+            for (x, z) in &starts {
+                let excavation = layout(technique, (*x, 0, *z), &context.options.direction, parameters)?;
+                for coords in excavation.mined() {
+                    below = below.min(coords.1);
+                    above = above.max(coords.1);
+                    for (x, _, z) in face_neighbours(coords).iter().chain(std::iter::once(&coords)) {
+                        columns.insert((*x, *z));
+                    }
+                }
+            }
+        }
+    }
+    // The blocks above and below those mined are exposed too
+    let mut world = World::new("regions", &context.cache);
+    world.preload(&columns, min + below - 1, max + above + 1)?;
+
+    let total = (max - min).max(0) as u64 * jobs.iter().map(|(_, sets, _)| sets.len() as u64).sum::<u64>();
+    let mut done = 0;
     for y in min..max {
-        let file_name = f_name.clone();
-        let tech = t.clone();
-        let results = simulate(file_name, tech, y, id, sender.clone());
-        sender.send(ProgramStatus::FinishSim(id)).unwrap();
-        csv_writer
-            .write_record(&[
-                y.to_string(),
-                results.get("blocks mined").unwrap().to_string(),
-                results.get("blocks exposed").unwrap().to_string(),
-                results.get("lava").unwrap().to_string(),
-                results.get("coal").unwrap().to_string(),
-                results.get("copper").unwrap().to_string(),
-                results.get("iron").unwrap().to_string(),
-                results.get("lapis").unwrap().to_string(),
-                results.get("redstone").unwrap().to_string(),
-                results.get("gold").unwrap().to_string(),
-                results.get("emeralds").unwrap().to_string(),
-                results.get("diamonds").unwrap().to_string(),
-            ])
-            .unwrap();
+        for (technique, sets, file) in jobs.iter_mut() {
+            for parameters in sets.iter() {
+                context.sender.send(ProgramStatus::ProgressSim(id, done, total))?;
+                done += 1;
+                if context.checkpoint.is_done(&region_file_name, &technique.name(), parameters, y) {
+                    continue;
+                }
+                context.cancel.check()?;
+                let results = simulate_in(
+                    &mut world,
+                    context,
+                    region_file_name.clone(),
+                    technique,
+                    parameters,
+                    y,
+                    id,
+                )?;
+                context.sender.send(ProgramStatus::FinishSim(id))?;
+                file.write(context, parameters, y, &results)?;
+            }
+        }
     }
+    return Ok(());
+}
+
+/// The results of a mining simulation in the order of the result file columns, see [mining_columns].
+pub fn mining_metrics(results: &HashMap<String, f64>, columns: &[String]) -> Vec<(String, f64)> {
+    return columns.iter().map(|c| (c.clone(), results[c])).collect();
 }
 
 pub fn simulate(
+    context: &SimulationContext,
     region_file_name: String,
-    technique: Technique,
+    technique: &Technique,
+    parameters: &Parameters,
     y: i32,
     id: u32,
-    sender: Sender<ProgramStatus>,
-) -> HashMap<String, i32> {
-    sender
-        .send(ProgramStatus::StartingSim(
-            id,
-            technique.clone(),
-            region_file_name.clone(),
-            Instant::now(),
-            y,
-        ))
-        .unwrap();
-    let region = Region::from_file(format!("regions/{}", region_file_name));
-    let mut r = CachingRegion::new(&region);
-    let sim_results = match technique {
-        Technique::Branch => branch_mining(
-            &mut r,
-            &Direction::South,
-            (255, y, 255),
-            16,
-            160,
-            5,
-            id,
-        ),
-        Technique::BranchWithPoke => branch_mining_with_poke_holes(
-            &mut r,
-            &Direction::South,
-            (255, y, 255),
-            10,
-            25,
-            5,
-            12,
+) -> Result<HashMap<String, f64>, SimulationError> {
+    let mut world = World::new("regions", &context.cache);
+    return simulate_in(
+        &mut world,
+        context,
+        region_file_name,
+        technique,
+        parameters,
+        y,
+        id,
+    );
+}
+
+/// Like [simulate] except that `world` is used, such as one that [simulate_region] has preloaded.
+fn simulate_in(
+    world: &mut World,
+    context: &SimulationContext,
+    region_file_name: String,
+    technique: &Technique,
+    parameters: &Parameters,
+    y: i32,
+    id: u32,
+) -> Result<HashMap<String, f64>, SimulationError> {
+    context.sender.send(ProgramStatus::StartingSim(
+        id,
+        technique.name(),
+        region_file_name.clone(),
+        Instant::now(),
+        y,
+    ))?;
+    let starts = absolute_starts(context, &region_file_name)?;
+    let mut totals = HashMap::new();
+    for (n, (x, z)) in starts.iter().enumerate() {
+        context.cancel.check()?;
+        let activity = if starts.len() == 1 {
+            String::from("Compiling Results")
+        } else {
+            format!(
+                "Start {} of {} ({}, {})",
+                n + 1,
+                starts.len(),
+                x.rem_euclid(REGION_WIDTH),
+                z.rem_euclid(REGION_WIDTH)
+            )
+        };
+        let results = simulate_from(
+            world,
+            context,
+            technique,
+            parameters,
+            (*x, y, *z),
+            activity,
             id,
-        ),
-        _ => unreachable!("Don't do a basic simulation on a non standard technique."),
-    };
-    let mut lava = 0;
-    let mut ores = Vec::new();
-    let valid = get_valid_blocks();
-    for block in sim_results.0 {
-        if block.block == "lava" || block.block == "flowing_lava" {
-            lava += 1;
-        } else if valid.contains_key(&block.block) {
-            ores.push(block);
+        )?;
+        for (column, value) in results {
+            *totals.entry(column).or_insert(0.0) += value;
         }
     }
+    for value in totals.values_mut() {
+        *value /= starts.len() as f64;
+    }
+    context.sender.send(ProgramStatus::CacheStats(world.cache_stats()))?;
+    return Ok(totals);
+}
 
-    let mut results = HashMap::new();
-    results.insert(String::from("iron"), 0);
-    results.insert(String::from("gold"), 0);
-    results.insert(String::from("diamonds"), 0);
-    results.insert(String::from("copper"), 0);
-    results.insert(String::from("redstone"), 0);
-    results.insert(String::from("lapis"), 0);
-    results.insert(String::from("coal"), 0);
-    results.insert(String::from("emeralds"), 0);
-    sender
-        .send(ProgramStatus::UpdateSim(
-            id,
-            format!("Compiling Results"),
-            sim_results.1,
-            sim_results.2,
-            lava as u32,
-            ores.len() as u32,
+/// The absolute x and z coordinates of every start of the options within the region, see [starts](`SimulationOptions::starts`).
+fn absolute_starts(
+    context: &SimulationContext,
+    region_file_name: &str,
+) -> Result<Vec<(i32, i32)>, SimulationError> {
+    let (region_x, region_z) = region_coords(region_file_name)
+        .ok_or_else(|| SimulationError::RegionName(region_file_name.to_string()))?;
+    return Ok(context
+        .options
+        .starts()
+        .iter()
+        .map(|(x, z)| (region_x * REGION_WIDTH + x, region_z * REGION_WIDTH + z))
+        .collect());
+}
+
+/// Lays out a technique with [run](`techniques::MiningTechnique::run`), an error names the technique and its parameters.
+fn layout(
+    technique: &Technique,
+    start: (i32, i32, i32),
+    direction: &Direction,
+    parameters: &Parameters,
+) -> Result<Excavation, SimulationError> {
+    return technique.run(start, direction, parameters).map_err(|e| {
+        SimulationError::Parameters(format!(
+            "{} with {}: {}",
+            technique.name(),
+            parameters_text(parameters),
+            e
         ))
-        .unwrap();
-    for mut ore in ores {
-        let key = valid.get(&mut ore.block).unwrap();
-        if let Some(c) = results.get_mut(key) {
-            *c += 1
+    });
+}
+
+/// Runs a technique once from a single starting position and tallies what it mined and exposed.
+fn simulate_from(
+    world: &mut World,
+    context: &SimulationContext,
+    technique: &Technique,
+    parameters: &Parameters,
+    start: (i32, i32, i32),
+    activity: String,
+    id: u32,
+) -> Result<HashMap<String, f64>, SimulationError> {
+    let options = &context.options;
+    let mut excavation = layout(technique, start, &options.direction, parameters)?;
+    if options.follow_veins {
+        follow_veins(world, &mut excavation)?;
+    }
+    let mut aborted = 0;
+    let mut planned_ores = 0;
+    if options.avoid_hazards {
+        planned_ores = survey(world, &excavation, &context.cancel)?
+            .blocks
+            .iter()
+            .filter(|b| world.palette().is_ore(b.block))
+            .count();
+        let (kept, count) = avoid_hazards(world, &excavation, start)?;
+        excavation = kept;
+        aborted = count;
+    }
+    let sim_results = survey(world, &excavation, &context.cancel)?;
+    let counts = world.palette().count(sim_results.blocks.iter().map(|b| b.block));
+    let ores_found = sim_results
+        .blocks
+        .iter()
+        .filter(|b| world.palette().is_ore(b.block))
+        .count();
+
+    let mut results = HashMap::new();
+    let mut lava = 0;
+    let mut ores = Vec::new();
+    for (category, count) in context.classification.categories().iter().zip(&counts) {
+        results.insert(category.name.clone(), *count as f64);
+        if category.name == "lava" {
+            lava = *count;
+        }
+        if category.ore {
+            ores.push((category.name.clone(), *count));
         }
     }
+    context.sender.send(ProgramStatus::UpdateSim(
+        id,
+        activity,
+        sim_results.mined,
+        sim_results.exposed,
+        lava,
+        ores,
+    ))?;
 
-    results.insert(String::from("blocks mined"), sim_results.1 as i32);
-    results.insert(String::from("blocks exposed"), sim_results.2 as i32);
-    results.insert(String::from("lava"), lava as i32);
-    // println!("Simulation took {} secs", timer.elapsed().as_secs());
-    return results;
+    results.insert(String::from("blocks mined"), sim_results.mined as f64);
+    results.insert(String::from("blocks exposed"), sim_results.exposed as f64);
+    results.insert(String::from("value"), context.classification.value(&counts));
+    results.insert(String::from("aborted segments"), aborted as f64);
+    results.insert(
+        String::from("ores lost"),
+        planned_ores.saturating_sub(ores_found) as f64,
+    );
+    return Ok(results);
 }
 
 pub fn chunk_analysis(
+    context: &SimulationContext,
     region_file_name: String,
     max: i32,
     min: i32,
     id: u32,
-    sender: Sender<ProgramStatus>,
-) {
-    fs::remove_file(format!("chunk_data/{}_chunks.csv", region_file_name,)).ok();
-    File::create(format!("chunk_data/{}_chunks.csv", region_file_name,)).unwrap();
-    let mut csv_writer =
-        csv::Writer::from_path(format!("chunk_data/{}_chunks.csv", region_file_name,)).unwrap();
-    csv_writer
-        .write_record(&[
-            "chunk_x", "chunk_z", "y", "air", "lava", "coal", "copper", "iron", "lapis",
-            "redstone", "gold", "emeralds", "diamonds",
-        ])
-        .unwrap();
-    let region = Region::from_file(format!("regions/{}", region_file_name));
-    sender
-        .send(ProgramStatus::StartingSim(
-            id,
-            Technique::Chunk,
-            region_file_name.clone(),
-            Instant::now(),
-            0,
-        ))
-        .unwrap();
-    sender
-        .send(ProgramStatus::UpdateSim(
-            id,
-            format!("Processing Chunks"),
-            0,
-            0,
-            0,
-            0,
-        ))
-        .unwrap();
+) -> Result<(), SimulationError> {
+    context.cancel.check()?;
+    let path = format!("chunk_data/{}_chunks.csv", region_file_name);
+    fs::remove_file(&path).ok();
+    let mut csv_writer = csv::Writer::from_path(&path)?;
+    let mut header = vec!["chunk_x", "chunk_z", "y"];
+    header.extend(context.classification.categories().iter().map(|c| c.name.as_str()));
+    csv_writer.write_record(&header)?;
+    let region_path = format!("regions/{}", region_file_name);
+    fs::metadata(&region_path).map_err(SimulationError::io(&region_path))?;
+    let region = Region::from_file(region_path);
+    let mut palette = Palette::new(&context.classification);
+    context.sender.send(ProgramStatus::StartingSim(
+        id,
+        String::from("chunk"),
+        region_file_name.clone(),
+        Instant::now(),
+        0,
+    ))?;
+    context.sender.send(ProgramStatus::UpdateSim(
+        id,
+        format!("Processing Chunks"),
+        0,
+        0,
+        0,
+        Vec::new(),
+    ))?;
     let mut tot_avg = 0;
     for x in 0..32 {
         for z in 0..32 {
+            if context.cancel.is_cancelled() {
+                csv_writer.flush().map_err(SimulationError::io(&path))?;
+                context.run.flush()?;
+                return Err(SimulationError::Cancelled);
+            }
+            context.sender.send(ProgramStatus::ProgressSim(id, (x * 32 + z) as u64, 32 * 32))?;
             let chunk = region.get_chunk(x, z);
             for y in min..max {
-                let (blocks, avg) = techniques::chunks(&chunk, y);
+                let (blocks, avg) = techniques::chunks(&chunk, y, &mut palette)?;
                 tot_avg += avg;
-                let mut lava = 0;
-                let mut ores = Vec::new();
-                let mut air = 0;
-                let valid = get_valid_blocks();
-                for block in blocks {
-                    if block.as_str() == "lava" || block.as_str() == "flowing_lava" {
-                        lava += 1;
-                    } else if block.as_str() == "air" {
-                        air += 1;
-                    } else if valid.contains_key(&block) {
-                        ores.push(block);
-                    }
-                }
-                // println!("{}", ores.len());
-                let mut results = HashMap::new();
-                results.insert(String::from("iron"), 0);
-                results.insert(String::from("gold"), 0);
-                results.insert(String::from("diamonds"), 0);
-                results.insert(String::from("copper"), 0);
-                results.insert(String::from("redstone"), 0);
-                results.insert(String::from("lapis"), 0);
-                results.insert(String::from("coal"), 0);
-                results.insert(String::from("emeralds"), 0);
-                for mut ore in ores {
-                    let key = valid.get(&mut ore).unwrap();
-                    if let Some(c) = results.get_mut(key) {
-                        *c += 1
-                    }
-                }
+                let counts = palette.count(blocks);
 
-                csv_writer
-                    .write_record(&[
-                        x.to_string(),
-                        z.to_string(),
-                        y.to_string(),
-                        air.to_string(),
-                        lava.to_string(),
-                        results.get("coal").unwrap().to_string(),
-                        results.get("copper").unwrap().to_string(),
-                        results.get("iron").unwrap().to_string(),
-                        results.get("lapis").unwrap().to_string(),
-                        results.get("redstone").unwrap().to_string(),
-                        results.get("gold").unwrap().to_string(),
-                        results.get("emeralds").unwrap().to_string(),
-                        results.get("diamonds").unwrap().to_string(),
-                    ])
-                    .unwrap();
+                let mut record = vec![x.to_string(), z.to_string(), y.to_string()];
+                record.extend(counts.iter().map(|c| c.to_string()));
+                csv_writer.write_record(&record)?;
+                let mut chunk_coords = Parameters::new();
+                chunk_coords.insert(String::from("chunk_x"), x as i32);
+                chunk_coords.insert(String::from("chunk_z"), z as i32);
+                let metrics: Vec<(&str, f64)> = context
+                    .classification
+                    .categories()
+                    .iter()
+                    .zip(&counts)
+                    .map(|(category, count)| (category.name.as_str(), *count as f64))
+                    .collect();
+                context.run.write(&region_file_name, "chunk", &chunk_coords, y, &metrics)?;
             }
         }
     }
-    sender.send(ProgramStatus::FinishSim(id)).unwrap();
+    csv_writer.flush().map_err(SimulationError::io(&path))?;
+    context.run.flush()?;
+    context.sender.send(ProgramStatus::FinishSim(id))?;
+    return Ok(());
+}
+
+/// Runs a simulation and reports it to the monitor with [FailedSim](`ProgramStatus::FailedSim`) if it returns an error or panics, such as when a region file is corrupt, so that the worker thread is not lost silently. Simulations that stop because they were cancelled are reported the same way with [Cancelled](`SimulationError::Cancelled`).
+pub fn report<F>(id: u32, context: SimulationContext, simulation: F)
+where
+    F: FnOnce(&SimulationContext) -> Result<(), SimulationError>,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| simulation(&context)));
+    let error = match result {
+        Ok(Ok(())) => return,
+        Ok(Err(e)) => e,
+        Err(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                String::from("unknown cause")
+            };
+            SimulationError::Panic(message)
+        }
+    };
+    // Nothing else can be done if the monitor has already stopped
+    context.sender.send(ProgramStatus::FailedSim(id, error)).ok();
 }
 
-fn get_valid_blocks() -> HashMap<String, String> {
-    let mut map = HashMap::new();
-    for line in BufReader::new(File::open("ValidBlocks.txt").unwrap()).lines() {
-        let line = line.unwrap();
-        let parts: Vec<&str> = line.split(":").collect();
-        let input = String::from(parts[0]);
-        let output = String::from(parts[1]);
-        map.insert(input, output);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(sampling: Sampling, reach: Reach) -> SimulationOptions {
+        return SimulationOptions {
+            sampling,
+            reach,
+            ..SimulationOptions::default()
+        };
+    }
+
+    #[test]
+    fn starts_leave_room_for_the_reach() {
+        let reach = Reach {
+            x: (-20, 300),
+            z: (-1, 1),
+        };
+        for sampling in [Sampling::Random(200, 7), Sampling::Grid(25)].iter() {
+            let starts = options(sampling.clone(), reach).starts();
+            assert!(!starts.is_empty());
+            for (x, z) in starts {
+                assert!((20..=211).contains(&x), "x {} with {:?}", x, sampling);
+                assert!((1..=510).contains(&z), "z {} with {:?}", z, sampling);
+            }
+        }
+    }
+
+    #[test]
+    fn starts_use_the_whole_region_when_the_reach_is_wider() {
+        let reach = Reach {
+            x: (-400, 400),
+            z: (0, 0),
+        };
+        for (x, z) in options(Sampling::Random(200, 3), reach).starts() {
+            assert!((0..REGION_WIDTH).contains(&x));
+            assert!((0..REGION_WIDTH).contains(&z));
+        }
+    }
+
+    #[test]
+    fn fixed_start_is_not_moved() {
+        let reach = Reach {
+            x: (-20, 300),
+            z: (-20, 300),
+        };
+        let mut options = options(Sampling::Fixed, reach);
+        options.start = (500, 3);
+        assert_eq!(options.starts(), vec![(500, 3)]);
+    }
+
+    #[test]
+    fn starts_must_be_within_the_region() {
+        assert_eq!(parse_start("0, 511"), Ok((0, 511)));
+        assert!(parse_start("512,0").is_err());
+        assert!(parse_start("0,-1").is_err());
+        assert!(parse_start("12").is_err());
+        assert!(parse_start("a,b").is_err());
+    }
+
+    #[test]
+    fn sampling_is_parsed() {
+        assert_eq!(Sampling::parse("fixed"), Ok(Sampling::Fixed));
+        assert_eq!(Sampling::parse(" random:5 "), Ok(Sampling::Random(5, 1)));
+        assert_eq!(Sampling::parse("random:5:42"), Ok(Sampling::Random(5, 42)));
+        assert_eq!(Sampling::parse("grid:9"), Ok(Sampling::Grid(9)));
+        for text in ["fixed:1", "random", "random:0", "random:5:x", "random:5:1:1", "grid", "grid:-1", "all"].iter() {
+            assert!(Sampling::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn sampling_text_is_parsed_back() {
+        for sampling in [Sampling::Fixed, Sampling::Random(3, 99), Sampling::Grid(16)].iter() {
+            assert_eq!(Sampling::parse(&sampling.text()).as_ref(), Ok(sampling));
+        }
     }
-    return map;
 }
@@ -0,0 +1,217 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    time::Duration,
+};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::{error::SimulationError, results::{RunMetadata, LONG_COLUMNS}};
+
+/// A single metric of a single simulation, the unit that every [ResultSink] stores.
+#[derive(Debug, Serialize)]
+pub struct ResultRow<'a> {
+    pub run_id: &'a str,
+    pub region: &'a str,
+    pub technique: &'a str,
+    /// Parameters in the form `name=value;name=value`.
+    pub params: &'a str,
+    pub y: i32,
+    pub metric: &'a str,
+    pub value: f64,
+}
+
+/// Somewhere that the long format results of a run are stored. Rows may be buffered until [flush](`ResultSink::flush`) is called, once it returns they should survive the program stopping.
+pub trait ResultSink: Send {
+    fn write(&mut self, row: &ResultRow) -> Result<(), SimulationError>;
+
+    fn flush(&mut self) -> Result<(), SimulationError>;
+
+    /// Stores the metadata of the run beside its results. Sinks that write a file for each run leave this to the json sidecar.
+    fn write_metadata(&mut self, _metadata: &RunMetadata) -> Result<(), SimulationError> {
+        return Ok(());
+    }
+}
+
+/// The kind of [ResultSink] that a run writes to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sink {
+    /// `run-<run_id>.csv` with a header row.
+    Csv,
+    /// `run-<run_id>.jsonl` with one json object per row.
+    JsonLines,
+    /// `results.sqlite` shared by every run, with a `results` table of rows and a `runs` table of metadata.
+    Sqlite,
+}
+
+impl Sink {
+    pub fn all() -> Vec<Sink> {
+        return vec![Sink::Csv, Sink::JsonLines, Sink::Sqlite];
+    }
+
+    /// Parses `csv`, `jsonl`, or `sqlite`.
+    pub fn parse(text: &str) -> Result<Sink, String> {
+        return Sink::all()
+            .into_iter()
+            .find(|s| s.name() == text.trim().to_lowercase())
+            .ok_or_else(|| format!("Result format '{}' should be csv, jsonl, or sqlite", text));
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Sink::Csv => return String::from("csv"),
+            Sink::JsonLines => return String::from("jsonl"),
+            Sink::Sqlite => return String::from("sqlite"),
+        }
+    }
+
+    /// The file in `directory` that a run writes its results to.
+    pub fn path(&self, directory: &str, run_id: &str) -> String {
+        match self {
+            Sink::Csv => return format!("{}/run-{}.csv", directory, run_id),
+            Sink::JsonLines => return format!("{}/run-{}.jsonl", directory, run_id),
+            Sink::Sqlite => return format!("{}/results.sqlite", directory),
+        }
+    }
+
+    pub fn open(&self, directory: &str, run_id: &str) -> Result<Box<dyn ResultSink>, SimulationError> {
+        let path = self.path(directory, run_id);
+        match self {
+            Sink::Csv => return Ok(Box::new(CsvSink::create(path)?)),
+            Sink::JsonLines => return Ok(Box::new(JsonLinesSink::create(path)?)),
+            Sink::Sqlite => return Ok(Box::new(SqliteSink::open(path)?)),
+        }
+    }
+}
+
+pub struct CsvSink {
+    path: String,
+    writer: csv::Writer<File>,
+}
+
+impl CsvSink {
+    pub fn create(path: String) -> Result<CsvSink, SimulationError> {
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_path(&path)?;
+        writer.write_record(LONG_COLUMNS)?;
+        writer.flush().map_err(SimulationError::io(&path))?;
+        return Ok(CsvSink { path, writer });
+    }
+}
+
+impl ResultSink for CsvSink {
+    fn write(&mut self, row: &ResultRow) -> Result<(), SimulationError> {
+        self.writer.serialize(row)?;
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> Result<(), SimulationError> {
+        self.writer.flush().map_err(SimulationError::io(&self.path))?;
+        return Ok(());
+    }
+}
+
+pub struct JsonLinesSink {
+    path: String,
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesSink {
+    pub fn create(path: String) -> Result<JsonLinesSink, SimulationError> {
+        let file = File::create(&path).map_err(SimulationError::io(&path))?;
+        return Ok(JsonLinesSink {
+            path,
+            writer: BufWriter::new(file),
+        });
+    }
+}
+
+impl ResultSink for JsonLinesSink {
+    fn write(&mut self, row: &ResultRow) -> Result<(), SimulationError> {
+        serde_json::to_writer(&mut self.writer, row)
+            .map_err(io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"))
+            .map_err(SimulationError::io(&self.path))?;
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> Result<(), SimulationError> {
+        self.writer.flush().map_err(SimulationError::io(&self.path))?;
+        return Ok(());
+    }
+}
+
+/// Rows are written inside a transaction that is committed on each flush, committing every row would be far slower.
+pub struct SqliteSink {
+    connection: Connection,
+    in_transaction: bool,
+}
+
+impl SqliteSink {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: String) -> Result<SqliteSink, SimulationError> {
+        let connection = Connection::open(&path)?;
+        // Other runs may be writing to the same database
+        connection.busy_timeout(Duration::from_secs(30))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS results (
+                run_id TEXT NOT NULL,
+                region TEXT NOT NULL,
+                technique TEXT NOT NULL,
+                params TEXT NOT NULL,
+                y INTEGER NOT NULL,
+                metric TEXT NOT NULL,
+                value REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS results_run ON results (run_id);
+            CREATE TABLE IF NOT EXISTS runs (
+                run_id TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL
+            );",
+        )?;
+        return Ok(SqliteSink {
+            connection,
+            in_transaction: false,
+        });
+    }
+}
+
+impl ResultSink for SqliteSink {
+    fn write(&mut self, row: &ResultRow) -> Result<(), SimulationError> {
+        if !self.in_transaction {
+            self.connection.execute_batch("BEGIN")?;
+            self.in_transaction = true;
+        }
+        self.connection
+            .prepare_cached("INSERT INTO results VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?
+            .execute(params![
+                row.run_id,
+                row.region,
+                row.technique,
+                row.params,
+                row.y,
+                row.metric,
+                row.value
+            ])?;
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> Result<(), SimulationError> {
+        if self.in_transaction {
+            self.connection.execute_batch("COMMIT")?;
+            self.in_transaction = false;
+        }
+        return Ok(());
+    }
+
+    /// The metadata is stored as json in the `runs` table, it can be read with sqlite's json functions such as `json_extract(metadata, '$.options.sampling')`.
+    fn write_metadata(&mut self, metadata: &RunMetadata) -> Result<(), SimulationError> {
+        let json = serde_json::to_string(metadata)
+            .map_err(|e| SimulationError::Io(String::from("results.sqlite"), io::Error::from(e)))?;
+        self.connection.execute(
+            "INSERT OR REPLACE INTO runs VALUES (?1, ?2)",
+            params![metadata.run_id, json],
+        )?;
+        return Ok(());
+    }
+}
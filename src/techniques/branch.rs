@@ -0,0 +1,243 @@
+use crate::mining::*;
+
+use super::{MiningTechnique, Parameter, Parameters};
+
+/// Pairs of long 2x1 branches off of a 2x1 main corridor.
+pub struct Branch;
+
+impl MiningTechnique for Branch {
+    fn name(&self) -> String {
+        return String::from("branch");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Branch");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![
+            Parameter::new("branch_pair_count", 16, 1),
+            Parameter::new("branch_length", 160, 1),
+            Parameter::new("branch_spacing", 5, 2),
+        ];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        branch_mining(
+            &mut excavation,
+            direction,
+            start,
+            parameters["branch_pair_count"],
+            parameters["branch_length"],
+            parameters["branch_spacing"],
+        );
+        return Ok(excavation);
+    }
+}
+
+/// Branch mining where the branches are 1x1 poke holes off of each side of a branch rather than a long tunnel.
+pub struct BranchWithPoke;
+
+impl MiningTechnique for BranchWithPoke {
+    fn name(&self) -> String {
+        return String::from("poke");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Branch with Poke Holes");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![
+            Parameter::new("branch_pair_count", 10, 1),
+            Parameter::new("pokes_per_branch", 25, 1),
+            Parameter::new("poke_spacing", 5, 1),
+            Parameter::new("branch_spacing", 12, 2),
+            Parameter::new("poke_depth", 5, 1),
+        ];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        branch_mining_with_poke_holes(
+            &mut excavation,
+            direction,
+            start,
+            parameters["branch_pair_count"],
+            parameters["pokes_per_branch"],
+            parameters["poke_spacing"],
+            parameters["branch_spacing"],
+            parameters["poke_depth"],
+        );
+        return Ok(excavation);
+    }
+}
+
+pub fn branch_mining(
+    excavation: &mut Excavation,
+    base_direction: &Direction,
+    starting_coords: (i32, i32, i32),
+    branch_pair_count: i32,
+    branch_length: i32,
+    branch_spacing: i32,
+) {
+    if branch_spacing < 2 {
+        panic!("Branch spacing should be at least two to avoid duplicates")
+    }
+
+    let (branch_direction1, branch_direction2) =
+        if base_direction == &Direction::East || base_direction == &Direction::West {
+            (&Direction::North, &Direction::South)
+        } else {
+            (&Direction::East, &Direction::West)
+        };
+    two_by_one_length(
+        excavation,
+        branch_direction1,
+        shift_coords(branch_direction1, starting_coords, 1),
+        branch_length,
+    );
+    two_by_one_length(
+        excavation,
+        branch_direction2,
+        shift_coords(branch_direction2, starting_coords, 1),
+        branch_length,
+    );
+    for n in 0..branch_pair_count - 1 {
+        expand_corridor(
+            excavation,
+            base_direction,
+            branch_spacing,
+            shift_coords(base_direction, starting_coords, n * branch_spacing),
+        );
+        two_by_one_length(
+            excavation,
+            branch_direction1,
+            shift_coords(
+                base_direction,
+                shift_coords(branch_direction1, starting_coords, 1),
+                (n + 1) * branch_spacing,
+            ),
+            branch_length,
+        );
+        two_by_one_length(
+            excavation,
+            branch_direction2,
+            shift_coords(
+                base_direction,
+                shift_coords(branch_direction2, starting_coords, 1),
+                (n + 1) * branch_spacing,
+            ),
+            branch_length,
+        );
+    }
+}
+
+pub fn branch_mining_with_poke_holes(
+    excavation: &mut Excavation,
+    base_direction: &Direction,
+    starting_coords: (i32, i32, i32),
+    branch_pair_count: i32,
+    pokes_per_branch: i32,
+    poke_spacing: i32,
+    branch_spacing: i32,
+    poke_depth: i32,
+) {
+    fn branch(
+        excavation: &mut Excavation,
+        pokes_per_branch: i32,
+        poke_spacing: i32,
+        poke_depth: i32,
+        direction: &Direction,
+        coords: (i32, i32, i32),
+    ) {
+        two_by_one_length(excavation, direction, coords, poke_spacing * pokes_per_branch);
+        for n in 0..pokes_per_branch {
+            let (poke_direction1, poke_direction2) =
+                if direction == &Direction::East || direction == &Direction::West {
+                    (&Direction::North, &Direction::South)
+                } else {
+                    (&Direction::East, &Direction::West)
+                };
+            let coords = shift_coords(direction, coords, n * poke_spacing - 1);
+            poke(
+                excavation,
+                poke_direction1,
+                shift_coords(poke_direction1, coords, 1),
+                poke_depth,
+            );
+            poke(
+                excavation,
+                poke_direction2,
+                shift_coords(poke_direction2, coords, 1),
+                poke_depth,
+            );
+        }
+    }
+
+    let (branch_direction1, branch_direction2) =
+        if base_direction == &Direction::East || base_direction == &Direction::West {
+            (&Direction::North, &Direction::South)
+        } else {
+            (&Direction::East, &Direction::West)
+        };
+    branch(
+        excavation,
+        pokes_per_branch,
+        poke_spacing,
+        poke_depth,
+        branch_direction1,
+        shift_coords(branch_direction1, starting_coords, 1),
+    );
+    branch(
+        excavation,
+        pokes_per_branch,
+        poke_spacing,
+        poke_depth,
+        branch_direction2,
+        shift_coords(branch_direction2, starting_coords, 1),
+    );
+    for n in 0..branch_pair_count - 1 {
+        expand_corridor(
+            excavation,
+            base_direction,
+            branch_spacing,
+            shift_coords(base_direction, starting_coords, n * branch_spacing),
+        );
+        branch(
+            excavation,
+            pokes_per_branch,
+            poke_spacing,
+            poke_depth,
+            branch_direction1,
+            shift_coords(
+                base_direction,
+                shift_coords(branch_direction1, starting_coords, 1),
+                (n + 1) * branch_spacing,
+            ),
+        );
+        branch(
+            excavation,
+            pokes_per_branch,
+            poke_spacing,
+            poke_depth,
+            branch_direction2,
+            shift_coords(
+                base_direction,
+                shift_coords(branch_direction2, starting_coords, 1),
+                (n + 1) * branch_spacing,
+            ),
+        );
+    }
+}
@@ -0,0 +1,712 @@
+//! Techniques that are described in a text file rather than in code. A definition is a list of the [mining](`crate::mining`) primitives that are run relative to a cursor, which starts at the starting coordinates facing the base direction.
+//!
+//! ```text
+//! # Comments start with '#'
+//! name = wide_branch
+//! display = Wide Branch
+//! param branch_pair_count = 8 min 1
+//! param branch_length = 64
+//! param branch_spacing = 8 min 3
+//!
+//! branch left {
+//!     tunnel branch_length
+//! }
+//! branch right {
+//!     tunnel branch_length
+//! }
+//! repeat branch_pair_count - 1 {
+//!     corridor branch_spacing
+//!     branch left {
+//!         tunnel branch_length
+//!     }
+//!     branch right {
+//!         tunnel branch_length
+//!     }
+//! }
+//! ```
+//!
+//! Steps:
+//! * `tunnel <n>` - Mines a 2x1 tunnel n blocks long, the cursor moves to the end of the tunnel.
+//! * `poke <side> <n>` - Mines a 1x1 poke hole n blocks deep to the side of the cursor, the cursor does not move.
+//! * `corridor <n>` - Expands the main corridor to the next pair of branches n blocks away, the cursor moves with it.
+//! * `move <n>` - Moves the cursor n blocks without mining.
+//!
+//! The n of these four can be from -4096 to 4096.
+//! * `turn <side>` - Turns the cursor.
+//! * `repeat <n> { ... }` - Runs the steps inside of the block n times, n can be from 0 to 1024.
+//! * `branch <side> { ... }` - Runs the steps inside of the block starting one block to the side of the cursor, the cursor returns to where it was afterwards.
+//!
+//! Sides are `forward`, `left`, `right`, or `back`. Numbers can be whole numbers, parameter names, or either combined with `+`, `-`, and `*` which are applied left to right and need spaces around them.
+//!
+//! A definition can run at most 1,000,000 steps, counting each time a repeat runs them, and mine at most 1,000,000 blocks, and the cursor has to stay within the world border. A definition that breaks any of these limits, or whose numbers overflow or are out of range, with its default parameters fails to load. Sweeps are checked the same way for each combination of parameters.
+
+use std::{fs, path::Path};
+
+use crate::mining::{expand_corridor, poke, two_by_one_length, Direction, Excavation};
+
+use super::{MiningTechnique, Parameter, Parameters};
+
+/// File extension of technique definitions inside of the definitions directory.
+pub const DEFINITION_EXTENSION: &str = "tech";
+
+/// Most times that a single `repeat` can run its steps.
+pub const MAX_REPEAT: i32 = 1024;
+
+/// Longest `tunnel`, `poke`, `corridor`, or `move` in either direction.
+pub const MAX_LENGTH: i32 = 4096;
+
+/// Most steps that a definition can run, each time a `repeat` runs its steps they are counted again.
+pub const MAX_STEPS: i64 = 1_000_000;
+
+/// Most blocks that a definition can mine, counting blocks that are mined more than once each time.
+pub const MAX_BLOCKS: i64 = 1_000_000;
+
+/// Furthest that the cursor can be from 0 along x or z, the world border of the game.
+const WORLD_BORDER: i64 = 30_000_000;
+
+#[derive(Clone, Debug)]
+enum Side {
+    Forward,
+    Left,
+    Right,
+    Back,
+}
+
+impl Side {
+    fn parse(text: &str) -> Result<Side, String> {
+        match text {
+            "forward" => Ok(Side::Forward),
+            "left" => Ok(Side::Left),
+            "right" => Ok(Side::Right),
+            "back" => Ok(Side::Back),
+            _ => Err(format!("'{}' is not a side (forward, left, right, back)", text)),
+        }
+    }
+
+    fn turn(&self, direction: &Direction) -> Direction {
+        match self {
+            Side::Forward => direction.clone(),
+            Side::Left => direction.left(),
+            Side::Right => direction.right(),
+            Side::Back => direction.opposite(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Number(i32),
+    Parameter(String),
+}
+
+/// A number in a definition, the operations are applied left to right.
+#[derive(Clone, Debug)]
+struct Expression {
+    first: Operand,
+    rest: Vec<(char, Operand)>,
+}
+
+impl Expression {
+    fn parse(tokens: &[&str], parameters: &[Parameter]) -> Result<Expression, String> {
+        let operand = |token: &str| -> Result<Operand, String> {
+            if let Ok(number) = token.parse::<i32>() {
+                return Ok(Operand::Number(number));
+            }
+            if parameters.iter().any(|p| p.name == token) {
+                return Ok(Operand::Parameter(token.to_string()));
+            }
+            return Err(format!("'{}' is not a number or a declared parameter", token));
+        };
+        if tokens.is_empty() || tokens.len() % 2 == 0 {
+            return Err(String::from("Expected a number"));
+        }
+        let mut rest = Vec::new();
+        for pair in tokens[1..].chunks(2) {
+            let op = match pair[0] {
+                "+" => '+',
+                "-" => '-',
+                "*" => '*',
+                other => return Err(format!("'{}' is not an operator (+, -, *)", other)),
+            };
+            rest.push((op, operand(pair[1])?));
+        }
+        return Ok(Expression {
+            first: operand(tokens[0])?,
+            rest,
+        });
+    }
+
+    /// The value with the parameters filled in, or an error if it does not fit in an i32.
+    fn evaluate(&self, parameters: &Parameters) -> Result<i32, String> {
+        let value = |operand: &Operand| match operand {
+            Operand::Number(n) => *n,
+            Operand::Parameter(name) => parameters[name],
+        };
+        let mut total = value(&self.first);
+        for (op, operand) in &self.rest {
+            let result = match op {
+                '+' => total.checked_add(value(operand)),
+                '-' => total.checked_sub(value(operand)),
+                _ => total.checked_mul(value(operand)),
+            };
+            total = result.ok_or_else(|| format!("{} overflows", self.text()))?;
+        }
+        return Ok(total);
+    }
+
+    /// The value of the length of a `step`, which can be at most [MAX_LENGTH] either way.
+    fn length(&self, step: &str, parameters: &Parameters) -> Result<i32, String> {
+        let length = self.evaluate(parameters)?;
+        if !(-MAX_LENGTH..=MAX_LENGTH).contains(&length) {
+            return Err(format!(
+                "{} {} is {}, it should be from {} to {}",
+                step,
+                self.text(),
+                length,
+                -MAX_LENGTH,
+                MAX_LENGTH
+            ));
+        }
+        return Ok(length);
+    }
+
+    /// The expression as it was written.
+    fn text(&self) -> String {
+        let text = |operand: &Operand| match operand {
+            Operand::Number(n) => n.to_string(),
+            Operand::Parameter(name) => name.clone(),
+        };
+        let mut result = text(&self.first);
+        for (op, operand) in &self.rest {
+            result.push_str(&format!(" {} {}", op, text(operand)));
+        }
+        return result;
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+    Tunnel(Expression),
+    Poke(Side, Expression),
+    Corridor(Expression),
+    Move(Expression),
+    Turn(Side),
+    Repeat(Expression, Vec<Step>),
+    Branch(Side, Vec<Step>),
+}
+
+/// Where the next step of a definition is run from.
+struct Cursor {
+    coords: (i32, i32, i32),
+    direction: Direction,
+}
+
+impl Cursor {
+    fn advance(&mut self, amount: i32) -> Result<(), String> {
+        self.coords = shifted(&self.direction, self.coords, amount)?;
+        return Ok(());
+    }
+}
+
+/// Like [shift_coords](`crate::mining::shift_coords`) except that coordinates that would be past the [WORLD_BORDER] are an error rather than overflowing.
+fn shifted(
+    direction: &Direction,
+    coords: (i32, i32, i32),
+    amount: i32,
+) -> Result<(i32, i32, i32), String> {
+    let (x, z) = match direction {
+        Direction::North => (coords.0 as i64, coords.2 as i64 - amount as i64),
+        Direction::South => (coords.0 as i64, coords.2 as i64 + amount as i64),
+        Direction::East => (coords.0 as i64 + amount as i64, coords.2 as i64),
+        Direction::West => (coords.0 as i64 - amount as i64, coords.2 as i64),
+    };
+    if x.abs() > WORLD_BORDER || z.abs() > WORLD_BORDER {
+        return Err(format!(
+            "the cursor moves past the world border, {} blocks from 0",
+            WORLD_BORDER
+        ));
+    }
+    return Ok((x as i32, coords.1, z as i32));
+}
+
+/// How much of [MAX_STEPS] and [MAX_BLOCKS] a run of a definition has used.
+#[derive(Default)]
+struct Budget {
+    steps: i64,
+    blocks: i64,
+}
+
+impl Budget {
+    fn step(&mut self) -> Result<(), String> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(format!("the definition runs more than {} steps", MAX_STEPS));
+        }
+        return Ok(());
+    }
+
+    /// Counts blocks before they are mined so that a definition that mines too many stops before it allocates them.
+    fn dig(&mut self, blocks: i32) -> Result<(), String> {
+        self.blocks += blocks.max(0) as i64;
+        if self.blocks > MAX_BLOCKS {
+            return Err(format!(
+                "the definition mines more than {} blocks",
+                MAX_BLOCKS
+            ));
+        }
+        return Ok(());
+    }
+}
+
+/// A technique compiled from a definition file.
+#[derive(Clone, Debug)]
+pub struct DeclaredTechnique {
+    name: String,
+    display_name: String,
+    parameters: Vec<Parameter>,
+    steps: Vec<Step>,
+}
+
+impl DeclaredTechnique {
+    /// Reads and compiles a definition file.
+    pub fn from_file(path: &Path) -> Result<DeclaredTechnique, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return DeclaredTechnique::parse(&text).map_err(|e| format!("{}:{}", path.display(), e));
+    }
+
+    /// Compiles the text of a definition, errors are prefixed with the line number that caused them.
+    pub fn parse(text: &str) -> Result<DeclaredTechnique, String> {
+        let mut name = None;
+        let mut display_name = None;
+        let mut parameters = Vec::new();
+        // Stack of blocks that are still open, the bottom is the top level of the definition
+        let mut blocks: Vec<(Option<Step>, Vec<Step>)> = vec![(None, Vec::new())];
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((before, _)) => before,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("{}: {}", i + 1, message);
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens[0] {
+                "name" | "display" => {
+                    let value = match line.split_once('=') {
+                        Some((_, value)) if !value.trim().is_empty() => value.trim().to_string(),
+                        _ => return Err(error(format!("Expected '{} = <value>'", tokens[0]))),
+                    };
+                    if tokens[0] == "name" {
+                        if value.contains(char::is_whitespace) {
+                            return Err(error(String::from("Names can not contain spaces")));
+                        }
+                        name = Some(value);
+                    } else {
+                        display_name = Some(value);
+                    }
+                }
+                "param" => {
+                    let parameter = match tokens.as_slice() {
+                        ["param", name, "=", default] => (name, default, "0"),
+                        ["param", name, "=", default, "min", min] => (name, default, *min),
+                        _ => {
+                            return Err(error(String::from(
+                                "Expected 'param <name> = <default>' optionally followed by 'min <value>'",
+                            )))
+                        }
+                    };
+                    let default = parameter
+                        .1
+                        .parse::<i32>()
+                        .map_err(|_| error(format!("'{}' is not a number", parameter.1)))?;
+                    let min = parameter
+                        .2
+                        .parse::<i32>()
+                        .map_err(|_| error(format!("'{}' is not a number", parameter.2)))?;
+                    if default < min {
+                        return Err(error(format!(
+                            "Default of {} is less than its minimum",
+                            parameter.0
+                        )));
+                    }
+                    if parameters.iter().any(|p: &Parameter| p.name == *parameter.0) {
+                        return Err(error(format!("{} is declared more than once", parameter.0)));
+                    }
+                    parameters.push(Parameter::new(parameter.0, default, min));
+                }
+                "}" => {
+                    if tokens.len() != 1 || blocks.len() == 1 {
+                        return Err(error(String::from("Unexpected '}'")));
+                    }
+                    let (step, steps) = blocks.pop().unwrap();
+                    let step = match step.unwrap() {
+                        Step::Repeat(count, _) => Step::Repeat(count, steps),
+                        Step::Branch(side, _) => Step::Branch(side, steps),
+                        _ => unreachable!("Only repeat and branch open blocks"),
+                    };
+                    blocks.last_mut().unwrap().1.push(step);
+                }
+                "repeat" | "branch" => {
+                    if tokens.len() < 3 || tokens[tokens.len() - 1] != "{" {
+                        return Err(error(format!("Expected '{} <value> {{'", tokens[0])));
+                    }
+                    let argument = &tokens[1..tokens.len() - 1];
+                    let step = if tokens[0] == "repeat" {
+                        Step::Repeat(Expression::parse(argument, &parameters).map_err(error)?, Vec::new())
+                    } else if argument.len() == 1 {
+                        Step::Branch(Side::parse(argument[0]).map_err(error)?, Vec::new())
+                    } else {
+                        return Err(error(String::from("Expected 'branch <side> {'")));
+                    };
+                    blocks.push((Some(step), Vec::new()));
+                }
+                _ => {
+                    let step = DeclaredTechnique::parse_step(&tokens, &parameters).map_err(error)?;
+                    blocks.last_mut().unwrap().1.push(step);
+                }
+            }
+        }
+        if blocks.len() != 1 {
+            return Err(String::from("end: A block is missing its closing '}'"));
+        }
+        let name = name.ok_or_else(|| String::from("end: Missing 'name = <name>'"))?;
+        let technique = DeclaredTechnique {
+            display_name: display_name.unwrap_or_else(|| name.clone()),
+            name,
+            parameters,
+            steps: blocks.pop().unwrap().1,
+        };
+        technique
+            .check(&technique.default_parameters())
+            .map_err(|e| format!("end: With the default parameters {}", e))?;
+        return Ok(technique);
+    }
+
+    fn parse_step(tokens: &[&str], parameters: &[Parameter]) -> Result<Step, String> {
+        let step = match tokens[0] {
+            "tunnel" => Step::Tunnel(Expression::parse(&tokens[1..], parameters)?),
+            "poke" if tokens.len() > 2 => Step::Poke(
+                Side::parse(tokens[1])?,
+                Expression::parse(&tokens[2..], parameters)?,
+            ),
+            "corridor" => Step::Corridor(Expression::parse(&tokens[1..], parameters)?),
+            "move" => Step::Move(Expression::parse(&tokens[1..], parameters)?),
+            "turn" if tokens.len() == 2 => Step::Turn(Side::parse(tokens[1])?),
+            _ => return Err(format!("Could not understand '{}'", tokens.join(" "))),
+        };
+        return Ok(step);
+    }
+
+    /// Runs the steps from the cursor, mining into `excavation` when it is given and otherwise only counting against the `budget`.
+    fn run_steps(
+        steps: &[Step],
+        cursor: &mut Cursor,
+        parameters: &Parameters,
+        budget: &mut Budget,
+        mut excavation: Option<&mut Excavation>,
+    ) -> Result<(), String> {
+        for step in steps {
+            budget.step()?;
+            match step {
+                Step::Tunnel(length) => {
+                    let length = length.length("tunnel", parameters)?;
+                    budget.dig(2 * length)?;
+                    // The far end is checked before mining so that every block of the tunnel is within the border
+                    let end = shifted(&cursor.direction, cursor.coords, length)?;
+                    if let Some(excavation) = excavation.as_deref_mut() {
+                        two_by_one_length(excavation, &cursor.direction, cursor.coords, length);
+                    }
+                    cursor.coords = end;
+                }
+                Step::Poke(side, depth) => {
+                    let depth = depth.length("poke", parameters)?;
+                    let direction = side.turn(&cursor.direction);
+                    if depth > 0 {
+                        budget.dig(depth)?;
+                        let coords = shifted(&direction, cursor.coords, 1)?;
+                        shifted(&direction, coords, depth)?;
+                        if let Some(excavation) = excavation.as_deref_mut() {
+                            poke(excavation, &direction, coords, depth);
+                        }
+                    }
+                }
+                Step::Corridor(spacing) => {
+                    let spacing = spacing.length("corridor", parameters)?;
+                    budget.dig(2 * (spacing + 1))?;
+                    let end = shifted(&cursor.direction, cursor.coords, spacing)?;
+                    shifted(&cursor.direction, end, 1)?;
+                    if let Some(excavation) = excavation.as_deref_mut() {
+                        expand_corridor(excavation, &cursor.direction, spacing, cursor.coords);
+                    }
+                    cursor.coords = end;
+                }
+                Step::Move(amount) => cursor.advance(amount.length("move", parameters)?)?,
+                Step::Turn(side) => cursor.direction = side.turn(&cursor.direction),
+                Step::Repeat(count, steps) => {
+                    let times = count.evaluate(parameters)?;
+                    if !(0..=MAX_REPEAT).contains(&times) {
+                        return Err(format!(
+                            "repeat {} is {}, it should be from 0 to {}",
+                            count.text(),
+                            times,
+                            MAX_REPEAT
+                        ));
+                    }
+                    for _ in 0..times {
+                        DeclaredTechnique::run_steps(
+                            steps,
+                            cursor,
+                            parameters,
+                            budget,
+                            excavation.as_deref_mut(),
+                        )?;
+                    }
+                }
+                Step::Branch(side, steps) => {
+                    let direction = side.turn(&cursor.direction);
+                    let mut branch = Cursor {
+                        coords: shifted(&direction, cursor.coords, 1)?,
+                        direction,
+                    };
+                    DeclaredTechnique::run_steps(
+                        steps,
+                        &mut branch,
+                        parameters,
+                        budget,
+                        excavation.as_deref_mut(),
+                    )?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl MiningTechnique for DeclaredTechnique {
+    fn name(&self) -> String {
+        return self.name.clone();
+    }
+
+    fn display_name(&self) -> String {
+        return self.display_name.clone();
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return self.parameters.clone();
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        let mut cursor = Cursor {
+            coords: start,
+            direction: direction.clone(),
+        };
+        DeclaredTechnique::run_steps(
+            &self.steps,
+            &mut cursor,
+            parameters,
+            &mut Budget::default(),
+            Some(&mut excavation),
+        )?;
+        return Ok(excavation);
+    }
+
+    /// Runs the steps from 0 without mining any blocks, to find numbers that overflow or are out of range and definitions that go past their limits.
+    fn check(&self, parameters: &Parameters) -> Result<(), String> {
+        let mut cursor = Cursor {
+            coords: (0, 0, 0),
+            direction: Direction::South,
+        };
+        return DeclaredTechnique::run_steps(
+            &self.steps,
+            &mut cursor,
+            parameters,
+            &mut Budget::default(),
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(technique: &DeclaredTechnique, values: &[(&str, i32)]) -> Parameters {
+        let mut parameters = technique.default_parameters();
+        for (name, value) in values {
+            parameters.insert(name.to_string(), *value);
+        }
+        return parameters;
+    }
+
+    #[test]
+    fn duplicate_parameters_are_rejected() {
+        let error = DeclaredTechnique::parse("name = t\nparam length = 4\nparam length = 5\ntunnel length")
+            .err()
+            .unwrap();
+        assert!(error.starts_with("3: "), "{}", error);
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        assert!(DeclaredTechnique::parse("name = t\ntunnel 2147483647 + 1").is_err());
+        let technique = DeclaredTechnique::parse("name = t\nparam length = 4\ntunnel length * 1000").unwrap();
+        assert!(technique.check(&parameters(&technique, &[("length", 4)])).is_ok());
+        assert!(technique
+            .check(&parameters(&technique, &[("length", i32::MAX / 10)]))
+            .is_err());
+    }
+
+    #[test]
+    fn repeat_counts_are_bounded() {
+        assert!(DeclaredTechnique::parse("name = t\nrepeat 0 - 1 {\ntunnel 1\n}").is_err());
+        assert!(DeclaredTechnique::parse("name = t\nrepeat 1025 {\ntunnel 1\n}").is_err());
+        let technique = DeclaredTechnique::parse("name = t\nparam count = 2\nrepeat count {\ntunnel 1\n}").unwrap();
+        assert!(technique.check(&parameters(&technique, &[("count", MAX_REPEAT)])).is_ok());
+        assert!(technique.check(&parameters(&technique, &[("count", MAX_REPEAT + 1)])).is_err());
+    }
+
+    #[test]
+    fn lengths_are_bounded() {
+        assert!(DeclaredTechnique::parse("name = t\ntunnel 4096\nmove 0 - 4096\npoke left 4096").is_ok());
+        for step in ["tunnel 4097", "poke left 4097", "corridor 4097", "move 0 - 4097"] {
+            let error = DeclaredTechnique::parse(&format!("name = t\n{}", step)).err().unwrap();
+            assert!(error.starts_with("end: "), "{}", error);
+        }
+    }
+
+    #[test]
+    fn nested_repeats_are_limited() {
+        let error = DeclaredTechnique::parse("name = t\nrepeat 1024 {\nrepeat 1024 {\nrepeat 1024 {\nturn left\n}\n}\n}")
+            .err()
+            .unwrap();
+        assert!(error.contains("steps"), "{}", error);
+        let error = DeclaredTechnique::parse("name = t\nrepeat 1024 {\nrepeat 4 {\ntunnel 4096\n}\n}")
+            .err()
+            .unwrap();
+        assert!(error.contains("blocks"), "{}", error);
+    }
+
+    #[test]
+    fn running_past_the_world_border_is_an_error() {
+        let technique = DeclaredTechnique::parse("name = t\nparam count = 1\nrepeat count {\nrepeat 8 {\nmove 4096\n}\n}\ntunnel 1").unwrap();
+        let far = parameters(&technique, &[("count", 1024)]);
+        // Run without being checked first
+        assert!(technique.run((0, 0, 0), &Direction::East, &far).is_err());
+        assert!(technique.run((0, 0, 0), &Direction::East, &technique.default_parameters()).is_ok());
+        assert!(technique
+            .run((29_999_999, 0, 0), &Direction::East, &technique.default_parameters())
+            .is_err());
+        assert!(technique
+            .run((i32::MIN, 0, 0), &Direction::West, &technique.default_parameters())
+            .is_err());
+    }
+
+    const WIDE_BRANCH: &str = "
+        # Comments are ignored
+        name = wide_branch
+        display = Wide Branch
+        param branch_pair_count = 8 min 1
+        param branch_length = 64
+        param branch_spacing = 8 min 3
+
+        branch left {
+            tunnel branch_length
+        }
+        branch right {
+            tunnel branch_length # so are comments after steps
+        }
+        repeat branch_pair_count - 1 {
+            corridor branch_spacing
+            branch left {
+                tunnel branch_length
+            }
+            branch right {
+                tunnel branch_length
+            }
+        }
+    ";
+
+    #[test]
+    fn definitions_are_parsed() {
+        let technique = DeclaredTechnique::parse(WIDE_BRANCH).unwrap();
+        assert_eq!(technique.name(), "wide_branch");
+        assert_eq!(technique.display_name(), "Wide Branch");
+        let parameters: Vec<(String, i32, i32)> = technique
+            .parameters()
+            .into_iter()
+            .map(|p| (p.name, p.default, p.min))
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                (String::from("branch_pair_count"), 8, 1),
+                (String::from("branch_length"), 64, 0),
+                (String::from("branch_spacing"), 8, 3),
+            ]
+        );
+        // Two branches for every pair, each 2 tall
+        let mut excavation = Excavation::new();
+        let mut cursor = Cursor {
+            coords: (0, 0, 0),
+            direction: Direction::South,
+        };
+        DeclaredTechnique::run_steps(
+            &technique.steps,
+            &mut cursor,
+            &technique.default_parameters(),
+            &mut Budget::default(),
+            Some(&mut excavation),
+        )
+        .unwrap();
+        let branches = excavation.segments().iter().filter(|s| s.len() == 64 * 2).count();
+        assert_eq!(branches, 16);
+        assert_eq!(cursor.coords, (0, 0, 7 * 8));
+    }
+
+    #[test]
+    fn display_name_defaults_to_the_name() {
+        let technique = DeclaredTechnique::parse("name = short\ntunnel 4").unwrap();
+        assert_eq!(technique.display_name(), "short");
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let error = |text: &str| DeclaredTechnique::parse(text).err().unwrap();
+        assert!(error("name = t\n\ntunnel").starts_with("3: "));
+        assert!(error("name = t\ntunnel length").starts_with("2: "));
+        assert!(error("name = t\nturn up").starts_with("2: "));
+        assert!(error("name = t\ndig 4").starts_with("2: "));
+        assert!(error("name = t\n}").starts_with("2: "));
+        assert!(error("name = two words").starts_with("1: "));
+        assert!(error("name = t\nparam a = 1 min 2").starts_with("2: "));
+        assert!(error("name = t\nrepeat 2 {\ntunnel 1").starts_with("end: "));
+        assert!(error("tunnel 1").starts_with("end: "));
+    }
+
+    #[test]
+    fn expressions_apply_left_to_right() {
+        let technique = DeclaredTechnique::parse("name = t\nparam a = 3\nmove a + 1 * 2 - a").unwrap();
+        let mut excavation = Excavation::new();
+        let mut cursor = Cursor {
+            coords: (0, 0, 0),
+            direction: Direction::East,
+        };
+        DeclaredTechnique::run_steps(
+            &technique.steps,
+            &mut cursor,
+            &technique.default_parameters(),
+            &mut Budget::default(),
+            Some(&mut excavation),
+        )
+        .unwrap();
+        assert_eq!(cursor.coords, (5, 0, 0));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::mining::*;
+
+use super::{MiningTechnique, Parameter, Parameters};
+
+/// A 2x1 main corridor with 2x1 branches that angle forward at 45 degrees, alternating between the left and right side of the corridor.
+pub struct Fishbone;
+
+impl MiningTechnique for Fishbone {
+    fn name(&self) -> String {
+        return String::from("fishbone");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Fishbone");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![
+            Parameter::new("branch_count", 32, 1),
+            Parameter::new("branch_length", 48, 1),
+            Parameter::new("branch_spacing", 4, 1),
+        ];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        let branch_count = parameters["branch_count"];
+        let branch_spacing = parameters["branch_spacing"];
+        two_by_one_length(
+            &mut excavation,
+            direction,
+            start,
+            branch_count * branch_spacing + 1,
+        );
+        for n in 0..branch_count {
+            let side = if n % 2 == 0 {
+                direction.left()
+            } else {
+                direction.right()
+            };
+            let junction = shift_coords(direction, start, (n + 1) * branch_spacing);
+            diagonal_branch(
+                &mut excavation,
+                direction,
+                &side,
+                shift_coords(&side, junction, 1),
+                parameters["branch_length"],
+            );
+        }
+        return Ok(excavation);
+    }
+}
+
+/// Mines a 2x1 branch that moves one block forward for every block to the side. An extra slice is mined between each diagonal step so that the branch can be walked through.
+fn diagonal_branch(
+    excavation: &mut Excavation,
+    forward: &Direction,
+    side: &Direction,
+    coords: (i32, i32, i32),
+    length: i32,
+) {
+    excavation.segment();
+    for n in 0..length {
+        let slice = shift_coords(forward, shift_coords(side, coords, n), n);
+        let connector = shift_coords(side, slice, 1);
+        for coords in [slice, connector].iter() {
+            excavation.dig(*coords);
+            excavation.dig((coords.0, coords.1 + 1, coords.2));
+        }
+    }
+}
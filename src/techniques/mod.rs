@@ -0,0 +1,371 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc, time::Instant};
+
+use mvp_anvil::chunk::Chunk;
+
+use crate::{block::{Palette, PaletteId}, mining::{BlockError, Direction, Excavation}};
+
+pub mod branch;
+pub mod declarative;
+pub mod fishbone;
+pub mod quarry;
+pub mod tunnel;
+
+/// Directory that technique definition files are loaded from.
+pub const DEFINITIONS_DIR: &str = "techniques";
+
+/// A mining layout that can be simulated. Techniques are discovered through the [TechniqueRegistry], so adding one only requires implementing this trait and registering it.
+pub trait MiningTechnique: Send + Sync {
+    /// Short name used in result file names, e.g. "branch".
+    fn name(&self) -> String;
+
+    /// Name shown in the ui, e.g. "Branch".
+    fn display_name(&self) -> String;
+
+    /// The knobs that shape the layout of the technique along with their default values.
+    fn parameters(&self) -> Vec<Parameter>;
+
+    /// Lays out the blocks that the technique mines starting at `start` heading in `direction`, without reading the world. `parameters` contains a value for every parameter returned by [parameters](`MiningTechnique::parameters`), an error is returned when the layout can not be made with them, see [check](`MiningTechnique::check`).
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String>;
+
+    /// Checks that the layout can be run with the parameters, every combination of a [sweep](`ParameterSweep`) is checked before it is simulated. Techniques that can be run with any parameters at or above their minimums do not need to implement this.
+    fn check(&self, _parameters: &Parameters) -> Result<(), String> {
+        return Ok(());
+    }
+
+    fn default_parameters(&self) -> Parameters {
+        return self
+            .parameters()
+            .iter()
+            .map(|p| (p.name.clone(), p.default))
+            .collect();
+    }
+}
+
+/// Shared handle to a technique so that it can be passed to the worker threads.
+pub type Technique = Arc<dyn MiningTechnique>;
+
+/// Every technique that is available to the ui, the command line, and the simulations.
+#[derive(Clone)]
+pub struct TechniqueRegistry {
+    techniques: Vec<Technique>,
+    errors: Vec<String>,
+}
+
+impl TechniqueRegistry {
+    /// Creates a registry containing all of the built in techniques followed by any techniques defined in the [definitions directory](DEFINITIONS_DIR). Definitions that fail to load or that reuse the name of another technique are skipped and recorded in [errors](`TechniqueRegistry::errors`).
+    pub fn load() -> TechniqueRegistry {
+        let mut registry = TechniqueRegistry {
+            techniques: Vec::new(),
+            errors: Vec::new(),
+        };
+        registry.register(Arc::new(branch::Branch));
+        registry.register(Arc::new(branch::BranchWithPoke));
+        registry.register(Arc::new(fishbone::Fishbone));
+        registry.register(Arc::new(tunnel::Tunnel { width: 1, height: 2 }));
+        registry.register(Arc::new(tunnel::Tunnel { width: 2, height: 2 }));
+        registry.register(Arc::new(tunnel::Tunnel { width: 3, height: 3 }));
+        registry.register(Arc::new(tunnel::Staircase));
+        registry.register(Arc::new(quarry::Strip));
+        registry.register(Arc::new(quarry::Quarry));
+        registry.load_definitions(Path::new(DEFINITIONS_DIR));
+        return registry;
+    }
+
+    fn load_definitions(&mut self, dir: &Path) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // Having no definitions is fine
+            Err(_) => return,
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.extension().and_then(|e| e.to_str()) == Some(declarative::DEFINITION_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            match declarative::DeclaredTechnique::from_file(&path) {
+                // Replacing a technique would quietly change what results with its name mean
+                Ok(technique) if self.techniques.iter().any(|t| t.name() == technique.name()) => {
+                    self.errors.push(format!(
+                        "{}: A technique named '{}' already exists",
+                        path.display(),
+                        technique.name()
+                    ))
+                }
+                Ok(technique) => self.register(Arc::new(technique)),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    /// Messages for the technique definitions that could not be loaded.
+    pub fn errors(&self) -> &[String] {
+        return &self.errors;
+    }
+
+    /// Adds a technique, a technique with the same name replaces the existing one.
+    pub fn register(&mut self, technique: Technique) {
+        match self
+            .techniques
+            .iter()
+            .position(|t| t.name() == technique.name())
+        {
+            Some(i) => self.techniques[i] = technique,
+            None => self.techniques.push(technique),
+        }
+    }
+
+    /// Looks up a technique by either its short name ("branch") or its display name ("Branch").
+    pub fn get(&self, name: &str) -> Option<Technique> {
+        return self
+            .techniques
+            .iter()
+            .find(|t| t.name() == name || t.display_name() == name)
+            .cloned();
+    }
+
+    pub fn techniques(&self) -> &[Technique] {
+        return &self.techniques;
+    }
+
+    pub fn display_names(&self) -> Vec<String> {
+        return self.techniques.iter().map(|t| t.display_name()).collect();
+    }
+}
+
+/// Values for the parameters of a technique keyed by the parameter name.
+pub type Parameters = BTreeMap<String, i32>;
+
+/// Parameters in the form `name=value;name=value` ordered by name, as they appear in the checkpoint and long format results.
+pub fn parameters_text(parameters: &Parameters) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    return parameters.join(";");
+}
+
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub default: i32,
+    /// The smallest value that still produces a valid layout.
+    pub min: i32,
+}
+
+impl Parameter {
+    pub fn new(name: &str, default: i32, min: i32) -> Parameter {
+        return Parameter {
+            name: name.to_string(),
+            default,
+            min,
+        };
+    }
+}
+
+/// An inclusive range of values that a single parameter is swept over.
+#[derive(Clone, Debug)]
+pub struct ParameterRange {
+    pub name: String,
+    pub start: i32,
+    pub end: i32,
+    pub step: i32,
+}
+
+impl ParameterRange {
+    /// Parses ranges in the form `start`, `start..end`, or `start..end:step` where end is inclusive.
+    pub fn parse(name: &str, text: &str) -> Result<ParameterRange, String> {
+        let number = |t: &str| {
+            t.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("Failed to parse '{}' for {}", t, name))
+        };
+        let (range, step) = match text.split_once(':') {
+            Some((range, step)) => (range, number(step)?),
+            None => (text, 1),
+        };
+        let (start, end) = match range.split_once("..") {
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(range)?, number(range)?),
+        };
+        if step < 1 {
+            return Err(format!("Step for {} should be greater than 0", name));
+        }
+        if start > end {
+            return Err(format!("Start of {} should not be greater than its end", name));
+        }
+        return Ok(ParameterRange {
+            name: name.to_string(),
+            start,
+            end,
+            step,
+        });
+    }
+
+    fn values(&self) -> Vec<i32> {
+        return (self.start..=self.end).step_by(self.step as usize).collect();
+    }
+}
+
+/// A set of parameter ranges, every combination of the values is simulated. Parameters that are not swept keep the default value of the technique.
+#[derive(Clone, Debug, Default)]
+pub struct ParameterSweep {
+    pub ranges: Vec<ParameterRange>,
+}
+
+impl ParameterSweep {
+    /// Every combination of parameter values for the technique. Ranges for parameters that the technique does not use are ignored, ranges that are empty or have a step below 1 are errors since they may not have come from [parse](`ParameterRange::parse`).
+    pub fn combinations(&self, technique: &dyn MiningTechnique) -> Result<Vec<Parameters>, String> {
+        let schema = technique.parameters();
+        let mut combinations = vec![technique.default_parameters()];
+        for range in &self.ranges {
+            if range.step < 1 {
+                return Err(format!("Step for {} should be greater than 0", range.name));
+            }
+            if range.start > range.end {
+                return Err(format!("Start of {} should not be greater than its end", range.name));
+            }
+            let parameter = match schema.iter().find(|p| p.name == range.name) {
+                Some(parameter) => parameter,
+                None => continue,
+            };
+            if range.start < parameter.min {
+                return Err(format!(
+                    "{} should be at least {}",
+                    parameter.name, parameter.min
+                ));
+            }
+            let mut expanded = Vec::new();
+            for combination in &combinations {
+                for value in range.values() {
+                    let mut combination = combination.clone();
+                    combination.insert(range.name.clone(), value);
+                    expanded.push(combination);
+                }
+            }
+            combinations = expanded;
+        }
+        for combination in &combinations {
+            technique
+                .check(combination)
+                .map_err(|e| format!("{} with {}: {}", technique.name(), parameters_text(combination), e))?;
+        }
+        return Ok(combinations);
+    }
+}
+
+/// Every block of a single y level of a chunk interned into the palette, along with the average time taken to read each block in nanoseconds.
+pub fn chunks(chunk: &Chunk, y: i32, palette: &mut Palette) -> Result<(Vec<PaletteId>, u128), BlockError> {
+    let mut results = Vec::new();
+    let mut total = 0;
+    for x in 0..16 {
+        for z in 0..16 {
+            let start = Instant::now();
+            results.push(palette.intern(&chunk.get_block(x, y, z))?);
+            total += start.elapsed().as_nanos();
+            // println!("Chunks block took: {}ns", start.elapsed().as_nanos())
+        }
+    }
+    // println!("Avg of {}ns", total / 256);
+    crate::count_blocks_read(results.len() as u64);
+    return Ok((results, total / 256));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sweep(ranges: Vec<ParameterRange>) -> ParameterSweep {
+        return ParameterSweep { ranges };
+    }
+
+    fn range(name: &str, start: i32, end: i32, step: i32) -> ParameterRange {
+        return ParameterRange {
+            name: name.to_string(),
+            start,
+            end,
+            step,
+        };
+    }
+
+    #[test]
+    fn combinations_cover_every_value() {
+        let combinations = sweep(vec![
+            range("branch_spacing", 3, 5, 1),
+            range("branch_length", 32, 96, 32),
+        ])
+        .combinations(&branch::Branch)
+        .unwrap();
+        assert_eq!(combinations.len(), 9);
+        assert!(combinations.iter().all(|c| c["branch_pair_count"] == 16));
+        assert_eq!(combinations[0]["branch_spacing"], 3);
+        assert_eq!(combinations[8]["branch_length"], 96);
+    }
+
+    #[test]
+    fn combinations_reject_bad_ranges() {
+        assert!(sweep(vec![range("branch_spacing", 5, 3, 1)]).combinations(&branch::Branch).is_err());
+        assert!(sweep(vec![range("branch_spacing", 3, 5, 0)]).combinations(&branch::Branch).is_err());
+        // Below the smallest spacing that still gives a valid layout
+        assert!(sweep(vec![range("branch_spacing", 1, 5, 1)]).combinations(&branch::Branch).is_err());
+    }
+
+    #[test]
+    fn combinations_are_checked_by_the_technique() {
+        let technique = declarative::DeclaredTechnique::parse("name = t\nparam count = 2\nrepeat count {\ntunnel 1\n}").unwrap();
+        assert!(sweep(vec![range("count", 1000, 1024, 24)]).combinations(&technique).is_ok());
+        assert!(sweep(vec![range("count", 1000, 2000, 1000)]).combinations(&technique).is_err());
+    }
+
+    #[test]
+    fn definitions_can_not_replace_other_techniques() {
+        let directory = std::env::temp_dir().join(format!("mcsim-definitions-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("a.tech"), "name = branch\ntunnel 4").unwrap();
+        fs::write(directory.join("b.tech"), "name = new\ntunnel 4").unwrap();
+        fs::write(directory.join("c.tech"), "name = new\ntunnel 8").unwrap();
+        let mut registry = TechniqueRegistry {
+            techniques: vec![Arc::new(branch::Branch)],
+            errors: Vec::new(),
+        };
+        registry.load_definitions(&directory);
+        assert_eq!(registry.techniques().len(), 2);
+        assert_eq!(registry.get("branch").unwrap().display_name(), "Branch");
+        assert_eq!(registry.errors().len(), 2);
+    }
+
+    #[test]
+    fn combinations_ignore_other_parameters() {
+        let combinations = sweep(vec![range("poke_depth", 1, 3, 1)])
+            .combinations(&branch::Branch)
+            .unwrap();
+        assert_eq!(combinations, vec![branch::Branch.default_parameters()]);
+    }
+
+    #[test]
+    fn ranges_are_parsed() {
+        let parsed = ParameterRange::parse("branch_spacing", "3..8").unwrap();
+        assert_eq!((parsed.start, parsed.end, parsed.step), (3, 8, 1));
+        let parsed = ParameterRange::parse("branch_length", " 32..160:32 ").unwrap();
+        assert_eq!((parsed.start, parsed.end, parsed.step), (32, 160, 32));
+        assert_eq!(parsed.values(), vec![32, 64, 96, 128, 160]);
+        let parsed = ParameterRange::parse("branch_spacing", "5").unwrap();
+        assert_eq!((parsed.start, parsed.end, parsed.step), (5, 5, 1));
+        assert_eq!(parsed.name, "branch_spacing");
+    }
+
+    #[test]
+    fn bad_ranges_are_not_parsed() {
+        for text in ["8..3", "1..5:0", "1..5:-1", "x", "1..", "..5", "1..5:", ""].iter() {
+            assert!(ParameterRange::parse("branch_spacing", text).is_err(), "{}", text);
+        }
+    }
+}
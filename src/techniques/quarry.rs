@@ -0,0 +1,87 @@
+use crate::mining::*;
+
+use super::{MiningTechnique, Parameter, Parameters};
+
+/// Clears a 2 tall layer over a rectangular area, one row at a time. The area extends forward and to the right of the starting coordinates.
+pub struct Strip;
+
+impl MiningTechnique for Strip {
+    fn name(&self) -> String {
+        return String::from("strip");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Strip Mining");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![
+            Parameter::new("strip_width", 16, 1),
+            Parameter::new("strip_length", 64, 1),
+        ];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        let right = direction.right();
+        for row in 0..parameters["strip_width"] {
+            two_by_one_length(
+                &mut excavation,
+                direction,
+                shift_coords(&right, start, row),
+                parameters["strip_length"],
+            );
+        }
+        return Ok(excavation);
+    }
+}
+
+/// Clears every block of a rectangular area layer by layer, starting at the simulated y level and working down. The area extends forward and to the right of the starting coordinates.
+pub struct Quarry;
+
+impl MiningTechnique for Quarry {
+    fn name(&self) -> String {
+        return String::from("quarry");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Quarry");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![
+            Parameter::new("quarry_width", 16, 1),
+            Parameter::new("quarry_length", 16, 1),
+            Parameter::new("quarry_depth", 16, 1),
+        ];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        let right = direction.right();
+        for layer in 0..parameters["quarry_depth"] {
+            for row in 0..parameters["quarry_width"] {
+                let row_start = shift_coords(&right, start, row);
+                tunnel(
+                    &mut excavation,
+                    direction,
+                    (row_start.0, row_start.1 - layer, row_start.2),
+                    1,
+                    1,
+                    parameters["quarry_length"],
+                );
+            }
+        }
+        return Ok(excavation);
+    }
+}
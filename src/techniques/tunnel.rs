@@ -0,0 +1,75 @@
+use crate::mining::*;
+
+use super::{MiningTechnique, Parameter, Parameters};
+
+/// A single straight tunnel with a fixed cross section.
+pub struct Tunnel {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl MiningTechnique for Tunnel {
+    fn name(&self) -> String {
+        return format!("tunnel{}x{}", self.width, self.height);
+    }
+
+    fn display_name(&self) -> String {
+        return format!("{}x{} Tunnel", self.width, self.height);
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![Parameter::new("tunnel_length", 256, 1)];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        tunnel(
+            &mut excavation,
+            direction,
+            start,
+            self.width,
+            self.height,
+            parameters["tunnel_length"],
+        );
+        return Ok(excavation);
+    }
+}
+
+/// A 1 wide staircase that descends one block for every block forward, starting at the simulated y level. Each step is 3 tall so that it can be walked down.
+pub struct Staircase;
+
+impl MiningTechnique for Staircase {
+    fn name(&self) -> String {
+        return String::from("staircase");
+    }
+
+    fn display_name(&self) -> String {
+        return String::from("Staircase");
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        return vec![Parameter::new("descent", 64, 1)];
+    }
+
+    fn run(
+        &self,
+        start: (i32, i32, i32),
+        direction: &Direction,
+        parameters: &Parameters,
+    ) -> Result<Excavation, String> {
+        let mut excavation = Excavation::new();
+        excavation.segment();
+        for n in 0..parameters["descent"] {
+            let step = shift_coords(direction, start, n);
+            for h in 0..3 {
+                excavation.dig((step.0, step.1 - n + h, step.2));
+            }
+        }
+        return Ok(excavation);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{sync::{Arc, Mutex, mpsc::Receiver}, time::{Duration, Instant}};
+
+use crate::{cache::CacheStats, error::SimulationError, CancelToken, ProgramStatus};
+
+use super::{cache_text, estimate, hms, print_summary, RunSummary};
+
+/// Replacement for [simulation_ui](`super::simulation::simulation_ui`) when running from the command line. Status messages are written to stderr as they arrive rather than drawing the tui so that output can be redirected to a log file.
+pub fn headless_ui(
+    receiver: Receiver<ProgramStatus>,
+    ender: Arc<Mutex<bool>>,
+    summary: RunSummary,
+    // There is no input to cancel from, Ctrl-C stops a headless run and --resume picks it back up
+    cancel: CancelToken,
+) {
+    let RunSummary {
+        title,
+        files,
+        threads,
+        techniques,
+        y_range,
+        planned_jobs,
+        start,
+    } = summary;
+    eprintln!(
+        "{}: {} region files, {} threads, {} techniques, y: [{}, {}], {} jobs planned",
+        title, files, threads, techniques, y_range.0, y_range.1, planned_jobs
+    );
+    let mut completed = 0;
+    let mut failures = Vec::new();
+    let mut stopped = 0;
+    let mut cache_stats = CacheStats::default();
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(status) => match status {
+                ProgramStatus::StartingSim(id, technique, file, _, y) => {
+                    eprintln!(
+                        "[{}] ({}) Starting {} on {} at y {}",
+                        elapsed(start),
+                        id,
+                        technique,
+                        file,
+                        y
+                    );
+                }
+                ProgramStatus::UpdateSim(id, activity, mined, exposed, lava, ores) => {
+                    let breakdown: Vec<String> = ores
+                        .iter()
+                        .map(|(ore, count)| format!("{} {}", ore, count))
+                        .collect();
+                    eprintln!(
+                        "[{}] ({}) {}: mined {}, exposed {}, lava {}, ores {} ({})",
+                        elapsed(start),
+                        id,
+                        activity,
+                        mined,
+                        exposed,
+                        lava,
+                        ores.iter().map(|(_, count)| count).sum::<u32>(),
+                        breakdown.join(", ")
+                    );
+                }
+                ProgramStatus::FailedSim(id, SimulationError::Cancelled) => {
+                    stopped += 1;
+                    eprintln!("[{}] ({}) Cancelled", elapsed(start), id);
+                }
+                ProgramStatus::FailedSim(id, error) => {
+                    eprintln!("[{}] ({}) Failed: {}", elapsed(start), id, error);
+                    failures.push(format!("({}) {}", id, error));
+                }
+                ProgramStatus::FinishSim(id) => {
+                    completed += 1;
+                    let (rate, left) = estimate(completed as u64, planned_jobs, start.elapsed());
+                    eprintln!(
+                        "[{}] ({}) Finished, {}/{} simulations completed, {:.2} jobs/sec, {:.0} blocks read/sec, {} left, {}",
+                        elapsed(start),
+                        id,
+                        completed,
+                        planned_jobs,
+                        rate,
+                        crate::blocks_read() as f64 / start.elapsed().as_secs_f64().max(0.001),
+                        left.map_or(String::from("unknown"), hms),
+                        cache_text(&cache_stats)
+                    );
+                }
+                ProgramStatus::ProgressSim(_, _, _) => {}
+                ProgramStatus::CacheStats(stats) => cache_stats = stats,
+            },
+            Err(_) => {
+                let end = ender.lock().unwrap();
+                if *end {
+                    print_summary(completed, &failures, stopped, cancel.is_cancelled());
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn elapsed(start: Instant) -> String {
+    return hms(start.elapsed());
+}
@@ -1,9 +1,10 @@
-use std::{collections::VecDeque, fs, iter::FromIterator, path::Path, process, time::Instant};
+use std::{collections::VecDeque, fs, io, iter::FromIterator, path::Path, process, time::{Duration, Instant}};
 
 use tui::widgets::ListState;
 
-use crate::techniques::Technique;
+use crate::{cache::CacheStats, simulations::{parse_start, Sampling, SimulationOptions}, techniques::{ParameterSweep, Technique, TechniqueRegistry}};
 
+pub mod headless;
 pub mod simulation;
 pub mod simulation_target;
 
@@ -71,7 +72,7 @@ impl<T> StatefulList<T> {
 #[derive(Clone)]
 pub struct Simulation {
     pub id: u32,
-    pub technique: Technique,
+    pub technique: String,
     pub file: String,
     pub activity: String,
     pub start: Instant,
@@ -79,11 +80,12 @@ pub struct Simulation {
     pub mined: u32,
     pub exposed: u32,
     pub lava: u32,
-    pub ores: u32,
+    /// Count of each category of ore from the latest update.
+    pub ores: Vec<(String, u32)>,
 }
 
 impl Simulation {
-    fn new(id: u32, technique: Technique, file: String, start: Instant, y: i32) -> Simulation {
+    fn new(id: u32, technique: String, file: String, start: Instant, y: i32) -> Simulation {
         return Simulation {
             id,
             technique,
@@ -94,43 +96,97 @@ impl Simulation {
             mined: 0,
             exposed: 0,
             lava: 0,
-            ores: 0,
+            ores: Vec::new(),
         };
     }
 }
 
+/// What a run is made up of, shown by the monitors.
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    pub title: String,
+    pub files: u32,
+    pub threads: u32,
+    pub techniques: u32,
+    pub y_range: (i32, i32),
+    /// Number of [FinishSim](`crate::ProgramStatus::FinishSim`) messages that will be sent, used for the estimated time left.
+    pub planned_jobs: u64,
+    pub start: Instant,
+}
+
 pub enum Simulations {
     Single(Technique, String, i32),
     Range(Technique, String, i32, i32),
     Techniques(Vec<Technique>, i32, i32, u32),
-    TechniqueParameters(Vec<Technique>, i32, i32, u32),
+    TechniqueParameters(Vec<Technique>, ParameterSweep, i32, i32, u32),
     Chunks(i32, i32, u32),
 }
 
+/// Jobs finished per second and the estimated time left before every planned job is finished. There is no estimate until the first job finishes.
+pub(crate) fn estimate(completed: u64, planned: u64, elapsed: Duration) -> (f64, Option<Duration>) {
+    let rate = completed as f64 / elapsed.as_secs_f64().max(0.001);
+    if completed == 0 {
+        return (rate, None);
+    }
+    let left = planned.saturating_sub(completed) as f64 / rate;
+    return (rate, Some(Duration::from_secs_f64(left)));
+}
+
+/// Hit rate and memory use of the chunk cache, for the monitors. The memory use is estimated from the number of chunks held.
+pub(crate) fn cache_text(stats: &CacheStats) -> String {
+    return format!(
+        "Chunk Cache: {:.1}% hits, {} chunks, ~{}/{} MiB, {} evicted",
+        stats.hit_rate() * 100.0,
+        stats.chunks,
+        stats.bytes / (1024 * 1024),
+        stats.budget / (1024 * 1024),
+        stats.evictions
+    );
+}
+
+/// Formats a duration as hh:mm:ss.
+pub(crate) fn hms(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    return format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60);
+}
+
+/// Printed by the monitors once the simulations have stopped and the terminal has been restored.
+pub(crate) fn print_summary(completed: u32, failures: &[String], stopped: u32, cancelled: bool) {
+    eprintln!("{} simulations completed", completed);
+    if cancelled {
+        eprintln!(
+            "Cancelled, {} simulations were stopped part way. Finished y levels are kept in the result files and can be skipped with --resume",
+            stopped
+        );
+    }
+    for failure in failures {
+        eprintln!("Failed {}", failure);
+    }
+}
+
 // Create mining_data, regions, if they are not already present. Fetch ValidBlocks.txt if it is not present.
-fn verify_directory_structure() -> bool {
+pub(crate) fn verify_directory_structure() -> io::Result<bool> {
     let mut regions = true;
-    // These two paths should be changed to create the dir and handle the error rather than 
     if !Path::new("mining_data/").exists() {
-        fs::create_dir("mining_data/").unwrap();
+        fs::create_dir("mining_data/")?;
     }
 
     if !Path::new("chunk_data/").exists() {
-        fs::create_dir("chunk_data/").unwrap();
+        fs::create_dir("chunk_data/")?;
     }
 
     if !Path::new("regions/").exists() {
-        fs::create_dir("regions/").unwrap();
+        fs::create_dir("regions/")?;
         regions = false;
-    } else if Path::new("regions/").read_dir().unwrap().next().is_none() {
+    } else if Path::new("regions/").read_dir()?.next().is_none() {
         regions = false;
     }
 
     if !Path::new("ValidBlocks.txt").exists() {
-        process::Command::new("curl").args(&["https://raw.githubusercontent.com/nuhtan/minecraft_analysis/master/ValidBlocks.txt", "-o", "ValidBlocks.txt"]).spawn().unwrap();
+        process::Command::new("curl").args(&["https://raw.githubusercontent.com/nuhtan/minecraft_analysis/master/ValidBlocks.txt", "-o", "ValidBlocks.txt"]).spawn()?;
     }
 
-    return regions;
+    return Ok(regions);
 }
 
 pub enum SimType {
@@ -164,7 +220,9 @@ pub enum UIRenderState {
     SimulationType,
     TechniqueSelect,
     TechniquesSelect,
+    ParameterRanges,
     ThreadCount,
+    Options,
     YLevel,
     YRange,
     RegionSelect,
@@ -174,6 +232,7 @@ pub enum UIRenderState {
 }
 
 pub struct UIState {
+    registry: TechniqueRegistry,
     sim_type: StatefulList<String>,
     technique: StatefulList<String>,
     techniques: Vec<usize>,
@@ -186,21 +245,32 @@ pub struct UIState {
     files: StatefulList<String>,
     threads: String,
     techniques_current: usize,
+    // Parameter name and the range typed for it
+    parameters: Vec<(String, String)>,
+    parameters_current: usize,
+    sweep: ParameterSweep,
+    options: SimulationOptions,
+    options_current: usize,
+    // Start coordinates and number of start points typed on the options screen
+    start: String,
+    samples: String,
 }
 
 impl UIState {
-    fn new() -> UIState {
+    fn new() -> io::Result<UIState> {
         let mut file_names = Vec::new();
-        for file in fs::read_dir("regions").unwrap() {
-            let file = file.unwrap();
+        for file in fs::read_dir("regions")? {
+            let file = file?;
             let f_name = file.file_name();
             let name = f_name.to_str().unwrap().to_string();
             let c_name = name.clone();
             file_names.push(c_name);
         }
-        return UIState {
+        let registry = TechniqueRegistry::load();
+        return Ok(UIState {
+            technique: StatefulList::with_items(VecDeque::from_iter(registry.display_names())),
+            registry,
             sim_type: StatefulList::with_items(VecDeque::from_iter(SimType::iterable())),
-            technique: StatefulList::with_items(VecDeque::from_iter(Technique::iterable())),
             techniques: Vec::new(),
             no_yes: (
                 StatefulList::with_items(VecDeque::from_iter(
@@ -220,11 +290,58 @@ impl UIState {
                 file_names
                     .iter()
                     .map(|f| f.as_str())
-                    .filter(|f| f.contains(".mca")) // Only include region files
+                    .filter(|f| crate::region_coords(f).is_some()) // Only include region files
                     .map(|f| f.to_string()), // change str to String
             )),
             threads: String::new(),
             techniques_current: 0,
+            parameters: Vec::new(),
+            parameters_current: 0,
+            sweep: ParameterSweep::default(),
+            options: SimulationOptions::default(),
+            options_current: 0,
+            start: String::from("255,255"),
+            samples: String::from("16"),
+        });
+    }
+
+    /// Rows of the options screen, the last row starts the simulation.
+    fn option_rows(&self) -> Vec<String> {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        return vec![
+            format!("Follow ore veins: {}", yes_no(self.options.follow_veins)),
+            format!("Avoid hazards: {}", yes_no(self.options.avoid_hazards)),
+            format!("Direction: {}", self.options.direction.name()),
+            format!("Start x,z: {}", self.start),
+            format!("Start points: {}", sampling_kind(&self.options.sampling)),
+            format!("Number of start points: {}", self.samples),
+            format!("Resume previous run: {}", yes_no(self.options.resume)),
+            format!("Result format: {}", self.options.sink.name()),
+            format!("Chunk cache: {} MiB", self.options.cache_budget),
+            format!("Single pass over each region: {}", yes_no(self.options.single_pass)),
+            String::from("Start Simulation"),
+        ];
+    }
+
+    /// Parses the start coordinates and number of start points that were typed into the options.
+    fn apply_start_options(&mut self) -> Result<(), String> {
+        self.options.start = parse_start(&self.start)?;
+        self.options.sampling = match self.options.sampling {
+            Sampling::Fixed => Sampling::Fixed,
+            _ => Sampling::parse(&format!(
+                "{}:{}",
+                sampling_kind(&self.options.sampling),
+                self.samples
+            ))?,
         };
+        return Ok(());
     }
-}
\ No newline at end of file
+}
+
+fn sampling_kind(sampling: &Sampling) -> &'static str {
+    match sampling {
+        Sampling::Fixed => "fixed",
+        Sampling::Random(_, _) => "random",
+        Sampling::Grid(_) => "grid",
+    }
+}
@@ -1,23 +1,28 @@
-use std::{collections::VecDeque, io, sync::{Arc, Mutex, mpsc::Receiver}, time::{Duration, Instant}};
+use std::{collections::{BTreeMap, HashMap, VecDeque}, io, sync::{Arc, Mutex, mpsc::Receiver}, time::Duration};
 
 use crossterm::{event::{self, Event, KeyCode, poll}, terminal::{self, disable_raw_mode}};
-use tui::{Terminal, backend::CrosstermBackend, layout::{Constraint, Direction, Layout}, style::{Color, Style}, text::Spans, widgets::{Block, Borders, List, ListItem, Paragraph}};
+use tui::{Terminal, backend::CrosstermBackend, layout::{Constraint, Direction, Layout}, style::{Color, Style}, text::Spans, widgets::{BarChart, Block, Borders, Gauge, List, ListItem, Paragraph, Wrap}};
 
-use crate::ProgramStatus;
+use crate::{cache::CacheStats, error::SimulationError, CancelToken, ProgramStatus};
 
-use super::{Simulation, StatefulList};
+use super::{cache_text, estimate, hms, print_summary, Simulation, StatefulList, RunSummary};
 
 
 pub fn simulation_ui(
     receiver: Receiver<ProgramStatus>,
     ender: Arc<Mutex<bool>>,
-    title: String,
-    files: u32,
-    threads: u32,
-    techniques: u32,
-    y_range: (i32, i32),
-    start: Instant,
+    summary: RunSummary,
+    cancel: CancelToken,
 ) {
+    let RunSummary {
+        title,
+        files,
+        threads,
+        techniques,
+        y_range,
+        planned_jobs,
+        start,
+    } = summary;
     let backend = CrosstermBackend::new(io::stdout());
     terminal::enable_raw_mode().unwrap();
     let mut terminal = Terminal::new(backend).unwrap();
@@ -25,6 +30,15 @@ pub fn simulation_ui(
     let mut state: StatefulList<Simulation> = StatefulList::with_items(VecDeque::new());
     state.state.select(Some(0));
     let mut completed = 0;
+    // Id and reason of each simulation that failed, most recent last
+    let mut failures: Vec<String> = Vec::new();
+    let mut stopped = 0;
+    // Units of work finished and total units of work for each simulation id
+    let mut progress: HashMap<u32, (u64, u64)> = HashMap::new();
+    // Ores found and blocks mined so far at each y level, for the chart
+    let mut by_y: BTreeMap<i32, (u64, u64)> = BTreeMap::new();
+    // Latest statistics of the shared chunk cache, sent as each simulation finishes
+    let mut cache_stats = CacheStats::default();
     loop {
         for _ in 0..25 {
             match receiver.recv_timeout(Duration::from_millis(1)) {
@@ -43,8 +57,28 @@ pub fn simulation_ui(
                         state.items[loc].mined = mined;
                         state.items[loc].exposed = exposed;
                         state.items[loc].lava = lava;
+                        if mined > 0 {
+                            let level = by_y.entry(state.items[loc].y).or_insert((0, 0));
+                            level.0 += ores.iter().map(|(_, n)| *n as u64).sum::<u64>();
+                            level.1 += mined as u64;
+                        }
                         state.items[loc].ores = ores;
                     }
+                    ProgramStatus::FailedSim(id, error) => {
+                        if let Some(loc) = state.items.iter().position(|s| s.id == id) {
+                            state.items.remove(loc);
+                            state.state.select(Some(0));
+                        }
+                        progress.remove(&id);
+                        match error {
+                            SimulationError::Cancelled => stopped += 1,
+                            error => failures.push(format!("({}) {}", id, error)),
+                        }
+                    }
+                    ProgramStatus::ProgressSim(id, done, total) => {
+                        progress.insert(id, (done, total));
+                    }
+                    ProgramStatus::CacheStats(stats) => cache_stats = stats,
                     ProgramStatus::FinishSim(id) => {
                         let mut loc = 0;
                         for i in 0..state.items.len() {
@@ -69,7 +103,11 @@ pub fn simulation_ui(
                     .split(f.size());
                 let left_sections = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .constraints([
+                        Constraint::Percentage(40),
+                        Constraint::Percentage(25),
+                        Constraint::Percentage(35),
+                    ])
                     .split(sections[0]);
                 let right_sections = Layout::default()
                     .direction(Direction::Vertical)
@@ -80,28 +118,53 @@ pub fn simulation_ui(
                 let hours = (start.elapsed().as_secs() / 3600) % 24;
                 let days = start.elapsed().as_secs() / (3600 * 24);
                 let top_right = Paragraph::new(vec![
-                    Spans::from(title.clone()),
+                    Spans::from(if cancel.is_cancelled() {
+                        format!("{} - Cancelling, waiting for running simulations to stop", title)
+                    } else {
+                        format!("{} - Press q or Esc to cancel", title)
+                    }),
                     Spans::from(format!(
                         "Duration: {:02}:{:02}:{:02}:{:02}",
                         days, hours, mins, secs
                     )),
                 ])
                 .block(Block::default().borders(Borders::ALL));
+                let (rate, left) = estimate(completed as u64, planned_jobs, start.elapsed());
                 let bot_right = Paragraph::new(vec![
                     Spans::from(format!("{} Region Files", files)),
                     Spans::from(format!("{} Threads Allocated", threads)),
-                    Spans::from(format!("{} Simulations Completed", completed)),
+                    Spans::from(format!(
+                        "{} of {} Simulations Completed",
+                        completed, planned_jobs
+                    )),
+                    Spans::from(format!("{:.2} Simulations/sec", rate)),
+                    Spans::from(format!(
+                        "{:.0} Blocks Read/sec",
+                        crate::blocks_read() as f64 / start.elapsed().as_secs_f64().max(0.001)
+                    )),
+                    Spans::from(cache_text(&cache_stats)),
+                    Spans::from(format!(
+                        "Estimated Time Left: {}",
+                        left.map_or(String::from("unknown"), hms)
+                    )),
                     Spans::from(format!("{} Techniques", techniques)),
                     Spans::from(format!("Y: [{}, {}]", y_range.0, y_range.1)),
+                    Spans::from(format!("{} Simulations Failed", failures.len())),
+                    Spans::from(failures.last().cloned().unwrap_or_default()),
                 ])
+                .wrap(Wrap { trim: true })
                 .block(Block::default().borders(Borders::ALL));
                 let items: Vec<ListItem> = state
                     .items
                     .iter()
                     .map(|i| {
+                        let percent = progress
+                            .get(&i.id)
+                            .map_or(String::new(), |p| format!(" [{:3.0}%]", ratio(*p) * 100.0));
                         ListItem::new(format!(
-                            "({}) {} - {:02}:{:02}",
+                            "({}){} {} - {:02}:{:02}",
                             i.id,
+                            percent,
                             i.activity,
                             i.start.elapsed().as_secs() / 60,
                             i.start.elapsed().as_secs() % 60
@@ -114,9 +177,14 @@ pub fn simulation_ui(
                 let bot_left_sections = Layout::default()
                     .direction(Direction::Horizontal)
                     .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-                    .split(left_sections[1]);
+                    .split(left_sections[2]);
+                let bot_left_left_sections = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(3)])
+                    .split(bot_left_sections[0]);
                 let bot_left_left;
                 let bot_left_right;
+                let mut gauge = Gauge::default().block(Block::default().borders(Borders::ALL));
                 if state.items.len() < 1 {
                     bot_left_left =
                         Paragraph::new("").block(Block::default().borders(Borders::ALL));
@@ -124,6 +192,12 @@ pub fn simulation_ui(
                         Paragraph::new("").block(Block::default().borders(Borders::ALL));
                 } else {
                     let target = state.items[state.state.selected().unwrap()].clone();
+                    if let Some(p) = progress.get(&target.id) {
+                        gauge = gauge
+                            .gauge_style(Style::default().fg(Color::Cyan))
+                            .ratio(ratio(*p))
+                            .label(format!("{} of {}", p.0, p.1));
+                    }
                     bot_left_left = Paragraph::new(vec![
                         Spans::from(target.file),
                         Spans::from(target.activity),
@@ -135,19 +209,51 @@ pub fn simulation_ui(
                         Spans::from(format!("Y: {}", target.y)),
                     ])
                     .block(Block::default().borders(Borders::ALL));
-                    bot_left_right = Paragraph::new(vec![
+                    let mut details = vec![
                         Spans::from(format!("Blocks Mined: {}", target.mined)),
                         Spans::from(format!("Blocks Exposed: {}", target.exposed)),
                         Spans::from(format!("Lava: {}", target.lava)),
-                        Spans::from(format!("Ores: {}", target.ores)),
-                    ])
-                    .block(Block::default().borders(Borders::ALL));
+                        Spans::from(format!(
+                            "Ores: {}",
+                            target.ores.iter().map(|(_, n)| n).sum::<u32>()
+                        )),
+                    ];
+                    // Two ores to a line so the breakdown fits beside the details
+                    for pair in target.ores.chunks(2) {
+                        let pair: Vec<String> =
+                            pair.iter().map(|(ore, n)| format!("{:>10}: {:<6}", ore, n)).collect();
+                        details.push(Spans::from(pair.join(" ")));
+                    }
+                    bot_left_right =
+                        Paragraph::new(details).block(Block::default().borders(Borders::ALL));
                 }
 
                 f.render_widget(top_right, right_sections[0]);
                 f.render_widget(bot_right, right_sections[1]);
+                // Ores per 1000 blocks mined at each y level, only the highest levels that fit are shown
+                let fits = (left_sections[1].width.saturating_sub(2) / 4) as usize;
+                let labels: Vec<String> = by_y.keys().map(|y| y.to_string()).collect();
+                let bars: Vec<(&str, u64)> = by_y
+                    .values()
+                    .zip(labels.iter())
+                    .map(|((ores, mined), y)| (y.as_str(), ores * 1000 / (*mined).max(1)))
+                    .collect();
+                let chart = BarChart::default()
+                    .block(
+                        Block::default()
+                            .title("Ores per 1000 Blocks Mined by Y")
+                            .borders(Borders::ALL),
+                    )
+                    .data(&bars[bars.len().saturating_sub(fits)..])
+                    .bar_width(3)
+                    .bar_gap(1)
+                    .bar_style(Style::default().fg(Color::Cyan))
+                    .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
                 f.render_stateful_widget(top_left, left_sections[0], &mut state.state);
-                f.render_widget(bot_left_left, bot_left_sections[0]);
+                f.render_widget(chart, left_sections[1]);
+                f.render_widget(bot_left_left, bot_left_left_sections[0]);
+                f.render_widget(gauge, bot_left_left_sections[1]);
                 f.render_widget(bot_left_right, bot_left_sections[1]);
             })
             .unwrap();
@@ -158,6 +264,7 @@ pub fn simulation_ui(
                 Event::Key(key) => match key.code {
                     KeyCode::Up => state.previous(),
                     KeyCode::Down => state.next(),
+                    KeyCode::Char('q') | KeyCode::Esc => cancel.cancel(),
                     _ => {}
                 },
                 _ => {}
@@ -172,4 +279,13 @@ pub fn simulation_ui(
     terminal.clear().unwrap();
     terminal.set_cursor(0, 0).unwrap();
     disable_raw_mode().unwrap();
-}
\ No newline at end of file
+    print_summary(completed, &failures, stopped, cancel.is_cancelled());
+}
+
+/// Fraction of the work that is finished, clamped so that it can be drawn by a gauge.
+fn ratio(progress: (u64, u64)) -> f64 {
+    if progress.1 == 0 {
+        return 0.0;
+    }
+    return (progress.0 as f64 / progress.1 as f64).min(1.0);
+}
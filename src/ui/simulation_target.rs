@@ -16,22 +16,30 @@ use tui::{
     Terminal,
 };
 
-use crate::techniques::Technique;
+use crate::{cache::CACHE_BUDGETS, simulations::{Sampling, SimulationOptions}, sinks::Sink, techniques::{ParameterRange, ParameterSweep, Technique}};
 
 use super::{verify_directory_structure, Simulations, UIRenderState, UIState};
 
-pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
+pub fn determine_simulation() -> Result<(bool, Option<(Simulations, SimulationOptions)>), Error> {
     // Determine if regions is empty
-    let backend = CrosstermBackend::new(io::stdout());
-    terminal::enable_raw_mode()?;
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
-    let mut current_state = if verify_directory_structure() {
+    let mut current_state = if verify_directory_structure()? {
         UIRenderState::SimulationType
     } else {
         UIRenderState::DirectoryStructure
     };
-    let mut state = UIState::new();
+    let mut state = UIState::new()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    terminal::enable_raw_mode()?;
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
+    if !state.registry.errors().is_empty() {
+        state.error.0 = format!(
+            "Failed to load technique definitions: {}",
+            state.registry.errors().join(", ")
+        );
+        state.error.1 = current_state;
+        current_state = UIRenderState::Error;
+    }
     let mut quit = false;
     let mut exit = false;
     loop {
@@ -118,7 +126,7 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                         "Please select techniques. (Use arrow keys and enter)",
                     ));
                     f.render_widget(top, sections[0]);
-                    let mut techs = Technique::iterable();
+                    let mut techs = state.registry.display_names();
                     techs.push(String::from("Done"));
                     let mut items = Vec::new();
                     for i in 0..techs.len() {
@@ -139,6 +147,40 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                     let list = List::new(items);
                     f.render_widget(list, sections[1]);
                 }
+                UIRenderState::ParameterRanges => {
+                    state.no_yes.1 = UIRenderState::ParameterRanges;
+                    state.error.1 = UIRenderState::ParameterRanges;
+                    let sections = Layout::default()
+                        .direction(Direction::Vertical)
+                        .margin(1)
+                        .constraints(
+                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
+                        )
+                        .split(f.size());
+                    let top = Paragraph::new(vec![
+                        Spans::from("Please enter parameter ranges. (Enter to submit)"),
+                        Spans::from("Ranges are 'value', 'start..end', or 'start..end:step', leave empty for the default."),
+                    ]);
+                    f.render_widget(top, sections[0]);
+                    let text = Paragraph::new(
+                        state
+                            .parameters
+                            .iter()
+                            .enumerate()
+                            .map(|(i, (name, range))| {
+                                Spans::from(Span::styled(
+                                    format!("{}: {}", name, range),
+                                    if i == state.parameters_current {
+                                        Style::default().fg(Color::Cyan)
+                                    } else {
+                                        Style::default()
+                                    },
+                                ))
+                            })
+                            .collect::<Vec<Spans>>(),
+                    );
+                    f.render_widget(text, sections[1]);
+                }
                 UIRenderState::ThreadCount => {
                     state.no_yes.1 = UIRenderState::ThreadCount;
                     state.error.1 = UIRenderState::ThreadCount;
@@ -228,6 +270,33 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                     let list = List::new(items).highlight_style(Style::default().fg(Color::Cyan));
                     f.render_stateful_widget(list, sections[1], &mut state.files.state);
                 }
+                UIRenderState::Options => {
+                    state.no_yes.1 = UIRenderState::Options;
+                    let sections = Layout::default()
+                        .direction(Direction::Vertical)
+                        .margin(1)
+                        .constraints(
+                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
+                        )
+                        .split(f.size());
+                    let top = Paragraph::new(Span::from(
+                        "Please choose simulation options. (Use arrow keys and enter to change, type to edit the start and number of start points)",
+                    ));
+                    f.render_widget(top, sections[0]);
+                    let rows = state.option_rows();
+                    let mut items = Vec::new();
+                    for i in 0..rows.len() {
+                        if i == state.options_current {
+                            items.push(
+                                ListItem::new(rows[i].clone())
+                                    .style(Style::default().fg(Color::Cyan)),
+                            );
+                        } else {
+                            items.push(ListItem::new(rows[i].clone()));
+                        }
+                    }
+                    f.render_widget(List::new(items), sections[1]);
+                }
                 UIRenderState::Simulate => exit = true,
                 UIRenderState::Quit => {
                     let sections = Layout::default()
@@ -282,6 +351,16 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                 state.techniques_current -= 1;
                             }
                         }
+                        UIRenderState::ParameterRanges => {
+                            if state.parameters_current > 0 {
+                                state.parameters_current -= 1;
+                            }
+                        }
+                        UIRenderState::Options => {
+                            if state.options_current > 0 {
+                                state.options_current -= 1;
+                            }
+                        }
                         UIRenderState::YRange => state.second_range = !state.second_range,
                         UIRenderState::RegionSelect => state.files.previous(),
                         UIRenderState::Quit => state.no_yes.0.previous(),
@@ -291,10 +370,20 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                         UIRenderState::SimulationType => state.sim_type.next(),
                         UIRenderState::TechniqueSelect => state.technique.next(),
                         UIRenderState::TechniquesSelect => {
-                            if state.techniques_current <= Technique::iterable().len() - 1 {
+                            if state.techniques_current < state.registry.techniques().len() {
                                 state.techniques_current += 1;
                             }
                         }
+                        UIRenderState::ParameterRanges => {
+                            if state.parameters_current + 1 < state.parameters.len() {
+                                state.parameters_current += 1;
+                            }
+                        }
+                        UIRenderState::Options => {
+                            if state.options_current + 1 < state.option_rows().len() {
+                                state.options_current += 1;
+                            }
+                        }
                         UIRenderState::YRange => state.second_range = !state.second_range,
                         UIRenderState::RegionSelect => state.files.next(),
                         UIRenderState::Quit => state.no_yes.0.next(),
@@ -312,7 +401,7 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                 "Single" => current_state = UIRenderState::TechniqueSelect,
                                 "Range" => current_state = UIRenderState::TechniqueSelect,
                                 "Techniques" => current_state = UIRenderState::TechniquesSelect,
-                                "Parameters" => current_state = UIRenderState::TechniqueSelect,
+                                "Parameters" => current_state = UIRenderState::TechniquesSelect,
                                 "Chunk" => current_state = UIRenderState::YRange,
                                 "Quit" => current_state = UIRenderState::Quit,
                                 _ => unreachable!("There was a string error"),
@@ -333,12 +422,32 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                         }
                         UIRenderState::TechniquesSelect => {
                             let i = state.techniques_current;
-                            if i == Technique::iterable().len() {
+                            if i == state.registry.techniques().len() {
                                 match state.sim_type.items[state.sim_type.state.selected().unwrap()]
                                     .as_str()
                                 {
                                     "Techniques" => current_state = UIRenderState::YRange,
-                                    "Parameters" => current_state = UIRenderState::YRange,
+                                    "Parameters" => {
+                                        // Every parameter used by at least one of the selected techniques
+                                        state.parameters.clear();
+                                        state.parameters_current = 0;
+                                        for t in &state.techniques {
+                                            let technique = &state.registry.techniques()[*t];
+                                            for parameter in technique.parameters() {
+                                                if !state
+                                                    .parameters
+                                                    .iter()
+                                                    .any(|(name, _)| *name == parameter.name)
+                                                {
+                                                    state.parameters.push((
+                                                        parameter.name.clone(),
+                                                        String::new(),
+                                                    ));
+                                                }
+                                            }
+                                        }
+                                        current_state = UIRenderState::ParameterRanges
+                                    }
                                     "Quit" => {
                                         current_state = UIRenderState::Quit;
                                     }
@@ -356,6 +465,36 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                 }
                             }
                         }
+                        UIRenderState::ParameterRanges => {
+                            let mut sweep = ParameterSweep::default();
+                            let mut error = None;
+                            for (name, range) in &state.parameters {
+                                if range.is_empty() {
+                                    continue;
+                                }
+                                match ParameterRange::parse(name, range) {
+                                    Ok(range) => sweep.ranges.push(range),
+                                    Err(e) => error = Some(e),
+                                }
+                            }
+                            for t in &state.techniques {
+                                let technique = &state.registry.techniques()[*t];
+                                if let Err(e) = sweep.combinations(technique.as_ref()) {
+                                    error = Some(e);
+                                }
+                            }
+                            match error {
+                                Some(e) => {
+                                    state.error.0 = e;
+                                    state.error.1 = UIRenderState::ParameterRanges;
+                                    current_state = UIRenderState::Error;
+                                }
+                                None => {
+                                    state.sweep = sweep;
+                                    current_state = UIRenderState::YRange;
+                                }
+                            }
+                        }
                         UIRenderState::ThreadCount => match state.threads.parse::<u32>() {
                             Ok(y) => {
                                 if y < 1 {
@@ -367,8 +506,8 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                         [state.sim_type.state.selected().unwrap()]
                                     .as_str()
                                     {
-                                        "Techniques" => current_state = UIRenderState::Simulate,
-                                        "Parameters" => current_state = UIRenderState::Simulate,
+                                        "Techniques" => current_state = UIRenderState::Options,
+                                        "Parameters" => current_state = UIRenderState::Options,
                                         "Chunk" => current_state = UIRenderState::Simulate,
                                         "Quit" => {
                                             current_state = UIRenderState::Quit;
@@ -434,8 +573,8 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                             match state.sim_type.items[state.sim_type.state.selected().unwrap()]
                                 .as_str()
                             {
-                                "Single" => current_state = UIRenderState::Simulate,
-                                "Range" => current_state = UIRenderState::Simulate,
+                                "Single" => current_state = UIRenderState::Options,
+                                "Range" => current_state = UIRenderState::Options,
                                 "Quit" => {
                                     current_state = UIRenderState::Quit;
                                 }
@@ -453,6 +592,46 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                 _ => {}
                             }
                         }
+                        UIRenderState::Options => match state.options_current {
+                            0 => state.options.follow_veins = !state.options.follow_veins,
+                            1 => state.options.avoid_hazards = !state.options.avoid_hazards,
+                            2 => state.options.direction = state.options.direction.right(),
+                            4 => {
+                                state.options.sampling = match state.options.sampling {
+                                    Sampling::Fixed => Sampling::Random(1, 1),
+                                    Sampling::Random(_, _) => Sampling::Grid(1),
+                                    Sampling::Grid(_) => Sampling::Fixed,
+                                }
+                            }
+                            6 => state.options.resume = !state.options.resume,
+                            7 => {
+                                let sinks = Sink::all();
+                                let current = sinks.iter().position(|s| *s == state.options.sink).unwrap_or(0);
+                                state.options.sink = sinks[(current + 1) % sinks.len()].clone();
+                            }
+                            8 => {
+                                let next = CACHE_BUDGETS.iter().find(|b| **b > state.options.cache_budget);
+                                state.options.cache_budget = *next.unwrap_or(&CACHE_BUDGETS[0]);
+                            }
+                            9 => {
+                                if state.sim_type.items[state.sim_type.state.selected().unwrap()] == "Single" {
+                                    state.error.0 = String::from("Single pass can only be used with range, techniques, and parameters simulations");
+                                    state.error.1 = UIRenderState::Options;
+                                    current_state = UIRenderState::Error;
+                                } else {
+                                    state.options.single_pass = !state.options.single_pass;
+                                }
+                            }
+                            3 | 5 => {}
+                            _ => match state.apply_start_options() {
+                                Ok(()) => current_state = UIRenderState::Simulate,
+                                Err(e) => {
+                                    state.error.0 = e;
+                                    state.error.1 = UIRenderState::Options;
+                                    current_state = UIRenderState::Error;
+                                }
+                            },
+                        },
                         UIRenderState::Error => current_state = state.error.1,
                         _ => {}
                     },
@@ -465,9 +644,33 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                     false => state.min.push(c),
                                     true => state.max.push(c),
                                 },
+                                UIRenderState::ParameterRanges => {
+                                    if let Some(p) = state.parameters.get_mut(state.parameters_current) {
+                                        p.1.push(c)
+                                    }
+                                }
+                                UIRenderState::Options => match state.options_current {
+                                    3 => state.start.push(c),
+                                    5 => state.samples.push(c),
+                                    _ => {}
+                                },
                                 _ => {}
                             }
                         }
+                        ',' => match current_state {
+                            UIRenderState::Options if state.options_current == 3 => {
+                                state.start.push(c)
+                            }
+                            _ => {}
+                        },
+                        '.' | ':' => match current_state {
+                            UIRenderState::ParameterRanges => {
+                                if let Some(p) = state.parameters.get_mut(state.parameters_current) {
+                                    p.1.push(c)
+                                }
+                            }
+                            _ => {}
+                        },
                         _ => {}
                     },
                     KeyCode::Backspace => match current_state {
@@ -477,6 +680,20 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                                     state.threads[0..state.threads.len() - 1].to_string();
                             }
                         }
+                        UIRenderState::ParameterRanges => {
+                            if let Some(p) = state.parameters.get_mut(state.parameters_current) {
+                                p.1.pop();
+                            }
+                        }
+                        UIRenderState::Options => match state.options_current {
+                            3 => {
+                                state.start.pop();
+                            }
+                            5 => {
+                                state.samples.pop();
+                            }
+                            _ => {}
+                        },
                         UIRenderState::YLevel => {
                             if state.y_level.len() > 0 {
                                 state.y_level =
@@ -515,16 +732,18 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
             let sim = match state.sim_type.items[state.sim_type.state.selected().unwrap()].as_str()
             {
                 "Single" => Simulations::Single(
-                    Technique::from_string(
-                        state.technique.items[state.technique.state.selected().unwrap()].clone(),
-                    ),
+                    state
+                        .registry
+                        .get(&state.technique.items[state.technique.state.selected().unwrap()])
+                        .unwrap(),
                     state.files.items[state.files.state.selected().unwrap()].clone(),
                     state.y_level.parse::<i32>().unwrap(),
                 ),
                 "Range" => Simulations::Range(
-                    Technique::from_string(
-                        state.technique.items[state.technique.state.selected().unwrap()].clone(),
-                    ),
+                    state
+                        .registry
+                        .get(&state.technique.items[state.technique.state.selected().unwrap()])
+                        .unwrap(),
                     state.files.items[state.files.state.selected().unwrap()].clone(),
                     state.min.parse::<i32>().unwrap(),
                     state.max.parse::<i32>().unwrap(),
@@ -533,7 +752,7 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                     state
                         .techniques
                         .iter()
-                        .map(|f| Technique::from_string(Technique::iterable().to_vec()[*f].clone()))
+                        .map(|f| state.registry.techniques()[*f].clone())
                         .collect::<Vec<Technique>>(),
                     state.min.parse::<i32>().unwrap(),
                     state.max.parse::<i32>().unwrap(),
@@ -543,8 +762,9 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                     state
                         .techniques
                         .iter()
-                        .map(|f| Technique::from_string(Technique::iterable().to_vec()[*f].clone()))
+                        .map(|f| state.registry.techniques()[*f].clone())
                         .collect::<Vec<Technique>>(),
+                    state.sweep.clone(),
                     state.min.parse::<i32>().unwrap(),
                     state.max.parse::<i32>().unwrap(),
                     state.threads.parse::<u32>().unwrap(),
@@ -556,7 +776,7 @@ pub fn determine_simulation() -> Result<(bool, Option<Simulations>), Error> {
                 ),
                 _ => unreachable!("There are a set number of paths"),
             };
-            return Ok((true, Some(sim)));
+            return Ok((true, Some((sim, state.options.clone()))));
         }
     }
 }